@@ -0,0 +1,102 @@
+use ariadne::{Color, ColorGenerator, Config, IndexType, Label, Report, ReportKind, Source};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// The report drawn by `examples/stresstest.rs`.
+fn stresstest_report() -> Report<(&'static str, std::ops::Range<usize>)> {
+    let mut colors = ColorGenerator::new();
+    let mut report = Report::build(ReportKind::Error, ("stresstest.tao", 13..13))
+        .with_message("Incompatible types")
+        .with_config(
+            Config::default()
+                .with_cross_gap(true)
+                .with_compact(false)
+                .with_underlines(true)
+                .with_tab_width(4)
+                .with_minimise_crossings(true),
+        );
+    for i in 0..21 {
+        report.add_label(
+            Label::new(("stresstest.tao", i..i + 1))
+                .with_message("Color")
+                .with_color(colors.next()),
+        );
+    }
+    for (span, msg) in [
+        (18..19, "This is of type Nat"),
+        (13..16, "This is of type Str"),
+        (40..41, "This is of type Nat"),
+        (43..47, "This is of type Bool"),
+        (49..51, "This is of type ()"),
+        (53..55, "This is of type [_]"),
+        (25..78, "This is of type Str"),
+        (81..124, "This is of type Nat"),
+        (100..126, "This is an inner multi-line"),
+        (106..120, "This is another inner multi-line"),
+        (108..122, "This is *really* nested multi-line"),
+        (110..111, "This is an inline within the nesting!"),
+        (111..112, "And another!"),
+        (103..123, "This is *really* nested multi-line"),
+        (105..125, "This is *really* nested multi-line"),
+        (112..116, "This is *really* nested multi-line"),
+        (26..100, "Hahaha!"),
+        (85..110, "Oh god, no more 1"),
+        (84..114, "Oh god, no more 2"),
+        (89..113, "Oh god, no more 3"),
+    ] {
+        let color = if msg == "Hahaha!" {
+            Color::Fixed(75)
+        } else {
+            colors.next()
+        };
+        report.add_label(
+            Label::new(("stresstest.tao", span))
+                .with_message(msg)
+                .with_color(color),
+        );
+    }
+    report.finish()
+}
+
+fn stresstest(c: &mut Criterion) {
+    let report = stresstest_report();
+    let source = Source::from(include_str!("../examples/stresstest.tao"));
+    c.bench_function("stresstest", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            report.write(("stresstest.tao", &source), &mut out).unwrap();
+            black_box(out)
+        })
+    });
+}
+
+/// Labels near the end of a long line of multi-byte text, given in bytes as a lexer would.
+fn multibyte_line(c: &mut Criterion) {
+    let line = "let naïve = \"αβγδε ζηθικ λμνξο\"; ".repeat(2000);
+    let end = line.len();
+    let report = Report::build(ReportKind::Error, end - 40..end - 30)
+        .with_config(Config::default().with_index_type(IndexType::Byte))
+        .with_message("Unexpected token")
+        .with_label(Label::new(end - 40..end - 30).with_message("here"))
+        .with_label(Label::new(end - 20..end - 10).with_message("and here"))
+        .finish();
+
+    let mut group = c.benchmark_group("multibyte_line");
+    for char_index in [false, true] {
+        let source = Source::from(line.clone()).with_char_index(char_index);
+        let name = if char_index { "char_index" } else { "plain" };
+        group.bench_function(format!("offset_to_byte/{name}"), |b| {
+            b.iter(|| source.offset_to_byte(black_box(source.len() - 35)))
+        });
+        group.bench_function(format!("render/{name}"), |b| {
+            b.iter(|| {
+                let mut out = Vec::new();
+                report.write(&source, &mut out).unwrap();
+                black_box(out)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, stresstest, multibyte_line);
+criterion_main!(benches);
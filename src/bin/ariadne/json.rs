@@ -0,0 +1,444 @@
+//! The JSON description of reports accepted by the command-line tool.
+
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use ariadne::{
+    CharSet, Color, Config, IndexType, Label, LabelAttach, LabelHighlight, Report, ReportKind,
+};
+use serde::Deserialize;
+
+/// The span type used for reports read from JSON.
+pub type JsonSpan<'a> = (&'a Path, Range<usize>);
+
+/// The top-level document: an optional config shared by every report, followed by the reports themselves.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Input {
+    #[serde(default)]
+    pub config: ConfigSpec,
+    pub reports: Vec<ReportSpec>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigSpec {
+    char_set: Option<CharSetSpec>,
+    color: Option<bool>,
+    compact: Option<bool>,
+    underlines: Option<bool>,
+    multiline_arrows: Option<bool>,
+    cross_gap: Option<bool>,
+    tab_width: Option<usize>,
+    context_lines: Option<usize>,
+    index_type: Option<IndexTypeSpec>,
+    label_attach: Option<LabelAttachSpec>,
+    minimise_crossings: Option<bool>,
+    show_invisibles: Option<bool>,
+    escape_bidi: Option<bool>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CharSetSpec {
+    Unicode,
+    Ascii,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum IndexTypeSpec {
+    Byte,
+    Char,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LabelAttachSpec {
+    Start,
+    Middle,
+    End,
+}
+
+impl ConfigSpec {
+    /// Apply the options that were given to `config`, leaving the others untouched.
+    pub fn apply(&self, mut config: Config) -> Config {
+        if let Some(char_set) = self.char_set {
+            config = config.with_char_set(match char_set {
+                CharSetSpec::Unicode => CharSet::Unicode,
+                CharSetSpec::Ascii => CharSet::Ascii,
+            });
+        }
+        if let Some(color) = self.color {
+            config = config.with_color(color);
+        }
+        if let Some(compact) = self.compact {
+            config = config.with_compact(compact);
+        }
+        if let Some(underlines) = self.underlines {
+            config = config.with_underlines(underlines);
+        }
+        if let Some(multiline_arrows) = self.multiline_arrows {
+            config = config.with_multiline_arrows(multiline_arrows);
+        }
+        if let Some(cross_gap) = self.cross_gap {
+            config = config.with_cross_gap(cross_gap);
+        }
+        if let Some(tab_width) = self.tab_width {
+            config = config.with_tab_width(tab_width);
+        }
+        if let Some(context_lines) = self.context_lines {
+            config = config.with_context_lines(context_lines);
+        }
+        if let Some(index_type) = self.index_type {
+            config = config.with_index_type(match index_type {
+                IndexTypeSpec::Byte => IndexType::Byte,
+                IndexTypeSpec::Char => IndexType::Char,
+            });
+        }
+        if let Some(label_attach) = self.label_attach {
+            config = config.with_label_attach(match label_attach {
+                LabelAttachSpec::Start => LabelAttach::Start,
+                LabelAttachSpec::Middle => LabelAttach::Middle,
+                LabelAttachSpec::End => LabelAttach::End,
+            });
+        }
+        if let Some(minimise_crossings) = self.minimise_crossings {
+            config = config.with_minimise_crossings(minimise_crossings);
+        }
+        if let Some(show_invisibles) = self.show_invisibles {
+            config = config.with_show_invisibles(show_invisibles);
+        }
+        if let Some(escape_bidi) = self.escape_bidi {
+            config = config.with_escape_bidi(escape_bidi);
+        }
+        config
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReportSpec {
+    kind: KindSpec,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+    /// The primary location of the report. Defaults to the start of the first label.
+    #[serde(default)]
+    location: Option<LocationSpec>,
+    #[serde(default)]
+    labels: Vec<LabelSpec>,
+    #[serde(default)]
+    notes: Vec<String>,
+    #[serde(default)]
+    helps: Vec<String>,
+    /// Further footer sections, such as `"Caused by"`.
+    #[serde(default)]
+    sections: Vec<SectionSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SectionSpec {
+    name: String,
+    #[serde(default)]
+    color: Option<ColorSpec>,
+    items: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KindSpec {
+    Builtin(BuiltinKind),
+    Custom { name: String, color: ColorSpec },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BuiltinKind {
+    Error,
+    Warning,
+    Advice,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocationSpec {
+    file: PathBuf,
+    offset: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LabelSpec {
+    file: PathBuf,
+    start: usize,
+    end: usize,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    color: Option<ColorSpec>,
+    #[serde(default)]
+    order: i32,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    highlight: Option<LabelHighlightSpec>,
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LabelHighlightSpec {
+    Foreground,
+    Background,
+    Bold,
+    Underline,
+}
+
+/// A color, given either as a 256-color palette index, a name such as `"red"`, or an `"#rrggbb"` hex string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorSpec {
+    Fixed(u8),
+    Named(String),
+}
+
+/// An error in the description of a report that could not be caught by deserialization alone.
+#[derive(Debug)]
+pub enum SpecError {
+    UnknownColor(String),
+    BackwardsLabel { file: PathBuf, span: Range<usize> },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecError::UnknownColor(name) => write!(f, "unknown color '{name}'"),
+            SpecError::BackwardsLabel { file, span } => write!(
+                f,
+                "label in '{}' ends ({}) before it starts ({})",
+                file.display(),
+                span.end,
+                span.start
+            ),
+        }
+    }
+}
+
+impl ColorSpec {
+    fn to_color(&self) -> Result<Color, SpecError> {
+        let name = match self {
+            ColorSpec::Fixed(n) => return Ok(Color::Fixed(*n)),
+            ColorSpec::Named(name) => name,
+        };
+        if let Some(hex) = name.strip_prefix('#') {
+            let channel = |i: usize| {
+                hex.get(i..i + 2)
+                    .and_then(|c| u8::from_str_radix(c, 16).ok())
+            };
+            return match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+                _ => Err(SpecError::UnknownColor(name.clone())),
+            };
+        }
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "white" => Color::White,
+            "bright-black" => Color::BrightBlack,
+            "bright-red" => Color::BrightRed,
+            "bright-green" => Color::BrightGreen,
+            "bright-yellow" => Color::BrightYellow,
+            "bright-blue" => Color::BrightBlue,
+            "bright-magenta" => Color::BrightMagenta,
+            "bright-cyan" => Color::BrightCyan,
+            "bright-white" => Color::BrightWhite,
+            _ => return Err(SpecError::UnknownColor(name.clone())),
+        })
+    }
+}
+
+impl ReportSpec {
+    /// Turn this description into a [`Report`], borrowing file paths from it.
+    pub fn to_report(&self, config: Config) -> Result<Report<JsonSpan<'_>>, SpecError> {
+        let kind = match &self.kind {
+            KindSpec::Builtin(BuiltinKind::Error) => ReportKind::Error,
+            KindSpec::Builtin(BuiltinKind::Warning) => ReportKind::Warning,
+            KindSpec::Builtin(BuiltinKind::Advice) => ReportKind::Advice,
+            // `ReportKind::Custom` wants a `&'static str`. This process renders a single document and then exits, so
+            // leaking the handful of custom kind names is harmless.
+            KindSpec::Custom { name, color } => {
+                ReportKind::Custom(Box::leak(name.clone().into_boxed_str()), color.to_color()?)
+            }
+        };
+
+        let span: JsonSpan = match (&self.location, self.labels.first()) {
+            (Some(location), _) => (location.file.as_path(), location.offset..location.offset),
+            (None, Some(label)) => (label.file.as_path(), label.start..label.start),
+            (None, None) => (Path::new(""), 0..0),
+        };
+
+        let mut builder = Report::build(kind, span).with_config(config);
+        builder.set_code(self.code.as_ref());
+        if let Some(message) = &self.message {
+            builder.set_message(message);
+        }
+        for label in &self.labels {
+            if label.start > label.end {
+                return Err(SpecError::BackwardsLabel {
+                    file: label.file.clone(),
+                    span: label.start..label.end,
+                });
+            }
+            let mut l = Label::new((label.file.as_path(), label.start..label.end))
+                .with_order(label.order)
+                .with_priority(label.priority);
+            if let Some(message) = &label.message {
+                l = l.with_message(message);
+            }
+            if let Some(color) = &label.color {
+                l = l.with_color(color.to_color()?);
+            }
+            if let Some(highlight) = label.highlight {
+                l = l.with_highlight(match highlight {
+                    LabelHighlightSpec::Foreground => LabelHighlight::Foreground,
+                    LabelHighlightSpec::Background => LabelHighlight::Background,
+                    LabelHighlightSpec::Bold => LabelHighlight::Bold,
+                    LabelHighlightSpec::Underline => LabelHighlight::Underline,
+                });
+            }
+            for note in &label.notes {
+                l = l.with_note(note);
+            }
+            builder.add_label(l);
+        }
+        builder.with_notes(&self.notes);
+        builder.with_helps(&self.helps);
+        for section in &self.sections {
+            if let Some(color) = &section.color {
+                builder = builder.with_section_color(&section.name, color.to_color()?);
+            }
+            for item in &section.items {
+                builder.add_section(&section.name, item);
+            }
+        }
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ariadne::{FileCache, OverlayCache};
+    use insta::assert_snapshot;
+
+    /// Parse a JSON document and render its reports against in-memory sources.
+    fn render(json: &str, files: &[(&'static str, &str)]) -> String {
+        let input: Input = serde_json::from_str(json).unwrap();
+        let config = input
+            .config
+            .apply(Config::default().with_index_type(IndexType::Byte));
+        let mut cache = OverlayCache::new(FileCache::default());
+        for (path, text) in files {
+            cache.insert(Path::new(path), text.to_string());
+        }
+        let mut out = Vec::new();
+        for spec in &input.reports {
+            spec.to_report(config)
+                .unwrap()
+                .write(&mut cache, &mut out)
+                .unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn builtin_kind() {
+        let out = render(
+            r#"{
+                "config": { "color": false },
+                "reports": [{
+                    "kind": "error",
+                    "code": "E001",
+                    "message": "Incompatible types",
+                    "labels": [
+                        { "file": "a.tao", "start": 8, "end": 9, "message": "This is of type Nat" },
+                        { "file": "a.tao", "start": 12, "end": 17, "message": "This is of type Str" }
+                    ],
+                    "notes": ["Outputs of match expressions must coerce to the same type"]
+                }]
+            }"#,
+            &[("a.tao", "let x = 5 + \"str\";")],
+        );
+        assert_snapshot!(out, @r#"
+        [E001] Error: Incompatible types
+           ╭─┤ a.tao:1:9 │
+           │
+         1 │ let x = 5 + "str";
+           │         ▲   ──┬──  
+           │         ╰─────│──── This is of type Nat
+           │               │    
+           │               ╰──── This is of type Str
+           │ 
+           │ Note: Outputs of match expressions must coerce to the same type
+        ───╯
+        "#);
+    }
+
+    #[test]
+    fn custom_kind() {
+        let out = render(
+            r##"{
+                "config": { "color": false, "compact": true },
+                "reports": [{
+                    "kind": { "name": "Lint", "color": "#ff8800" },
+                    "message": "Unused variable",
+                    "labels": [{ "file": "b.tao", "start": 4, "end": 5, "message": "Never used" }]
+                }]
+            }"##,
+            &[("b.tao", "let y = 1;")],
+        );
+        assert_snapshot!(out, @"
+        Lint: Unused variable
+           ╭─┤ b.tao:1:5 │
+         1 │ let y = 1;
+           │     ▲ 
+           │     ╰─ Never used
+        ");
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let input: Input = serde_json::from_str(
+            r#"{ "reports": [
+                { "kind": { "name": "Lint", "color": "mauve" } },
+                { "kind": "error", "labels": [{ "file": "a.tao", "start": 3, "end": 1 }] }
+            ] }"#,
+        )
+        .unwrap();
+        let errors: Vec<_> = input
+            .reports
+            .iter()
+            .map(|spec| spec.to_report(Config::default()).unwrap_err().to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "unknown color 'mauve'",
+                "label in 'a.tao' ends (1) before it starts (3)"
+            ]
+        );
+    }
+}
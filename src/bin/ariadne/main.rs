@@ -0,0 +1,235 @@
+//! `ariadne`: render diagnostics described in JSON.
+//!
+//! This lets tools that are not written in Rust produce the same output as those that use the library directly. The
+//! input document is read from the file given as the only argument, or from stdin if no file (or `-`) is given.
+//!
+//! ```json
+//! {
+//!     "config": { "char_set": "ascii", "color": false, "compact": true },
+//!     "reports": [
+//!         {
+//!             "kind": "error",
+//!             "code": "E001",
+//!             "message": "Incompatible types",
+//!             "labels": [
+//!                 { "file": "sample.tao", "start": 32, "end": 33, "message": "This is of type Nat", "color": "red" },
+//!                 { "file": "sample.tao", "start": 52, "end": 55, "message": "This is of type Str" }
+//!             ],
+//!             "notes": ["Outputs of match expressions must coerce to the same type"]
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! Label spans are byte ranges unless `"index_type": "char"` is given in the config. Source files are loaded from
+//! disk, relative to the current directory.
+//!
+//! With `--rustc`, the input is instead the line-delimited JSON emitted by `rustc --error-format=json` or
+//! `cargo build --message-format=json`, so that `cargo build --message-format=json | ariadne --rustc` re-renders
+//! rustc's diagnostics. Lines that are not JSON are passed through untouched.
+
+mod json;
+
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    process::ExitCode,
+};
+
+use ariadne::{rustc, AnsiMode, CharSet, Config, FileCache, Hyperlinks, IndexType, OutputMode};
+
+const USAGE: &str = "\
+Usage: ariadne [OPTIONS] [FILE]
+
+Render the reports described by the JSON document in FILE (or stdin, if FILE is `-` or absent).
+
+Options:
+    --rustc       Read rustc/cargo JSON diagnostics, one per line, instead of a JSON document
+    --ascii       Draw using ASCII characters only
+    --no-color    Disable colored output. By default, output is only colored when writing to a terminal, and the
+                  NO_COLOR, CLICOLOR and CLICOLOR_FORCE environment variables are respected
+    --compact     Use compact output
+    --hyperlinks  Link the location of each source to its file, for terminals that support it
+    --format <FORMAT>
+                  The output format: `full` (the default), `short`, `github`, `gitlab`
+                  or `quickfix`
+    --short-labels
+                  List the location of each label in the `short` format
+    -h, --help    Print this message";
+
+#[derive(Default)]
+struct Options {
+    path: Option<String>,
+    rustc: bool,
+    ascii: bool,
+    no_color: bool,
+    compact: bool,
+    hyperlinks: bool,
+    output_mode: Option<OutputMode>,
+    short_labels: bool,
+}
+
+impl Options {
+    /// Apply the options given on the command line, which take priority over those given in the input.
+    fn apply(&self, mut config: Config) -> Config {
+        if self.ascii {
+            config = config.with_char_set(CharSet::Ascii);
+        }
+        if self.no_color {
+            config = config.with_color(false);
+        }
+        if self.compact {
+            config = config.with_compact(true);
+        }
+        if self.hyperlinks {
+            config = config.with_hyperlinks(Hyperlinks::File);
+        }
+        if let Some(output_mode) = self.output_mode {
+            config = config.with_output_mode(output_mode);
+        }
+        if self.short_labels {
+            config = config.with_short_labels(true);
+        }
+        config
+    }
+
+    fn input(&self) -> io::Result<Box<dyn BufRead>> {
+        Ok(match self.path.as_deref() {
+            None | Some("-") => Box::new(io::stdin().lock()),
+            Some(path) => Box::new(BufReader::new(fs::File::open(path)?)),
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            "--rustc" => options.rustc = true,
+            "--ascii" => options.ascii = true,
+            "--no-color" => options.no_color = true,
+            "--compact" => options.compact = true,
+            "--hyperlinks" => options.hyperlinks = true,
+            "--short-labels" => options.short_labels = true,
+            "--format" => {
+                options.output_mode = match args.next().as_deref() {
+                    Some("full") => Some(OutputMode::Full),
+                    Some("short") => Some(OutputMode::Short),
+                    Some("github") => Some(OutputMode::GitHub),
+                    Some("gitlab") => Some(OutputMode::GitLab),
+                    Some("quickfix") => Some(OutputMode::Quickfix),
+                    format => {
+                        eprintln!("unknown format '{}'\n\n{USAGE}", format.unwrap_or(""));
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            _ if options.path.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                options.path = Some(arg)
+            }
+            _ => {
+                eprintln!("unexpected argument '{arg}'\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let input = match options.input() {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read input: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // GitLab wants a single array of issues, but each report is written as a separate line of JSON
+    let gitlab = options.output_mode == Some(OutputMode::GitLab);
+    let mut issues = Vec::new();
+    let mut stdout = io::stdout().lock();
+    let out: &mut dyn Write = if gitlab { &mut issues } else { &mut stdout };
+    let result = if options.rustc {
+        render_rustc(&options, input, out)
+    } else {
+        render_json(&options, input, out)
+    }
+    .and_then(|()| {
+        if gitlab {
+            let issues = String::from_utf8_lossy(&issues);
+            writeln!(stdout, "[{}]", issues.lines().collect::<Vec<_>>().join(","))
+                .and_then(|()| stdout.flush())
+        } else {
+            stdout.flush()
+        }
+        .map_err(|err| format!("failed to write report: {err}"))
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn default_config() -> Config {
+    Config::default().with_ansi_mode(AnsiMode::Auto)
+}
+
+fn render_json(
+    options: &Options,
+    mut input: Box<dyn BufRead>,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let mut text = String::new();
+    input
+        .read_to_string(&mut text)
+        .map_err(|err| format!("failed to read input: {err}"))?;
+    let input: json::Input =
+        serde_json::from_str(&text).map_err(|err| format!("invalid input: {err}"))?;
+
+    let config = options.apply(
+        input
+            .config
+            .apply(default_config().with_index_type(IndexType::Byte)),
+    );
+    let mut cache = FileCache::default();
+    for spec in &input.reports {
+        let report = spec
+            .to_report(config)
+            .map_err(|err| format!("invalid report: {err}"))?;
+        report
+            .write_for_stdout(&mut cache, &mut *out)
+            .map_err(|err| format!("failed to write report: {err}"))?;
+    }
+    Ok(())
+}
+
+fn render_rustc(
+    options: &Options,
+    input: Box<dyn BufRead>,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let config = options.apply(default_config());
+    let mut cache = FileCache::default();
+    for line in input.lines() {
+        let line = line.map_err(|err| format!("failed to read input: {err}"))?;
+        match rustc::parse_message(&line) {
+            Ok(Some(diagnostic)) => diagnostic
+                .to_report(config)
+                .write_for_stdout(&mut cache, &mut *out),
+            Ok(None) => Ok(()),
+            // Passing other output through would make the JSON invalid, so move it out of the way
+            Err(_) if options.output_mode == Some(OutputMode::GitLab) => {
+                eprintln!("{line}");
+                Ok(())
+            }
+            Err(_) => writeln!(out, "{line}"),
+        }
+        .map_err(|err| format!("failed to write report: {err}"))?;
+    }
+    Ok(())
+}
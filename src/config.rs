@@ -1,7 +1,12 @@
+use std::borrow::Cow;
+
 use unicode_width::UnicodeWidthChar;
 use yansi::Color;
 
-use crate::LabelAttach;
+use crate::{
+    draw::{self, CharDisplay},
+    LabelAttach,
+};
 
 /// A type used to configure a report
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -20,6 +25,14 @@ pub struct Config {
     pub(crate) ansi_mode: AnsiMode,
     pub(crate) enumerate_notes: bool,
     pub(crate) enumerate_helps: bool,
+    pub(crate) enumerate_sections: bool,
+    pub(crate) output_mode: OutputMode,
+    pub(crate) short_labels: bool,
+    pub(crate) hyperlinks: Hyperlinks,
+    pub(crate) color_depth: Option<ColorDepth>,
+    pub(crate) show_invisibles: bool,
+    pub(crate) escape_bidi: bool,
+    pub(crate) locale: Locale,
 }
 
 impl Config {
@@ -106,6 +119,8 @@ impl Config {
     }
     /// Should ANSI escape code styling be included in the diagnostic after writing?
     ///
+    /// [`AnsiMode::Auto`] decides based on the environment and on whether the output is a terminal.
+    ///
     /// If unspecified, this defaults to `AnsiMode::On`.
     pub const fn with_ansi_mode(mut self, ansi_mode: AnsiMode) -> Self {
         self.ansi_mode = ansi_mode;
@@ -128,41 +143,175 @@ impl Config {
         self
     }
 
+    /// Should separate items of custom footer sections be numbered?
+    ///
+    /// See [`ReportBuilder::with_section`](crate::ReportBuilder::with_section).
+    ///
+    /// If unspecified, this defaults to [`true`]
+    pub const fn with_enumerated_sections(mut self, enumerate_sections: bool) -> Self {
+        self.enumerate_sections = enumerate_sections;
+        self
+    }
+
+    /// In what format should reports be written?
+    ///
+    /// If unspecified, this defaults to [`OutputMode::Full`].
+    pub const fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// When using [`OutputMode::Short`], should the location of each label be listed on its own line beneath the
+    /// report?
+    ///
+    /// If unspecified, this defaults to [`false`]
+    pub const fn with_short_labels(mut self, short_labels: bool) -> Self {
+        self.short_labels = short_labels;
+        self
+    }
+
+    /// Should the location of each source be a hyperlink, for terminals that support them?
+    ///
+    /// Links point at the path given by [`Cache::absolute_path`](crate::Cache::absolute_path), so sources without
+    /// one are left as plain text.
+    ///
+    /// If unspecified, this defaults to [`Hyperlinks::Off`].
+    pub const fn with_hyperlinks(mut self, hyperlinks: Hyperlinks) -> Self {
+        self.hyperlinks = hyperlinks;
+        self
+    }
+
+    /// How many colors can the terminal display?
+    ///
+    /// Every color used by the report, including those of labels, report kinds and [`ColorGenerator`]s, is converted
+    /// to the closest color available at this depth. Colors that are already embedded in messages (such as those added
+    /// with [`Fmt`](crate::Fmt)) are left as they are.
+    ///
+    /// If unspecified, colors are written as given.
+    ///
+    /// [`ColorGenerator`]: crate::ColorGenerator
+    pub const fn with_color_depth(mut self, color_depth: ColorDepth) -> Self {
+        self.color_depth = Some(color_depth);
+        self
+    }
+
+    /// Should invisible characters in the source, such as tabs, non-breaking spaces, zero-width spaces and control
+    /// characters, be drawn with visible placeholders?
+    ///
+    /// Tabs are drawn as `␉`, carriage returns as `␍` and non-breaking spaces as `·`. Other unusual whitespace, control
+    /// and formatting characters are drawn as their code point, such as `<U+200B>`. Trailing whitespace is kept. Label
+    /// arrows and underlines stay aligned with the placeholders.
+    ///
+    /// If unspecified, this defaults to [`false`].
+    pub const fn with_show_invisibles(mut self, show_invisibles: bool) -> Self {
+        self.show_invisibles = show_invisibles;
+        self
+    }
+
+    /// Should bidirectional override and isolate characters (U+202A to U+202E and U+2066 to U+2069) be escaped?
+    ///
+    /// These characters can make a terminal display text in a different order to the one in which it is written, so
+    /// that a snippet does not show what the source actually contains (see [Trojan Source](https://trojansource.codes)).
+    /// When escaped, they are drawn as their code point, such as `<U+202E>`, in snippets, messages, source names and
+    /// every output mode.
+    ///
+    /// If unspecified, this defaults to [`true`].
+    pub const fn with_escape_bidi(mut self, escape_bidi: bool) -> Self {
+        self.escape_bidi = escape_bidi;
+        self
+    }
+
+    /// Which words should be used for the fixed parts of reports, such as `Error` and `Help`?
+    ///
+    /// If unspecified, this defaults to [`Locale::new`], which is in English.
+    pub const fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
     pub(crate) fn error_color(&self) -> Option<Color> {
-        Some(Color::Red).filter(|_| self.color)
+        self.filter_color(Some(Color::Red))
     }
     pub(crate) fn warning_color(&self) -> Option<Color> {
-        Some(Color::Yellow).filter(|_| self.color)
+        self.filter_color(Some(Color::Yellow))
     }
     pub(crate) fn advice_color(&self) -> Option<Color> {
-        Some(Color::Fixed(147)).filter(|_| self.color)
+        self.filter_color(Some(Color::Fixed(147)))
     }
     pub(crate) fn margin_color(&self) -> Option<Color> {
-        Some(Color::Fixed(246)).filter(|_| self.color)
+        self.filter_color(Some(Color::Fixed(246)))
     }
     pub(crate) fn skipped_margin_color(&self) -> Option<Color> {
-        Some(Color::Fixed(240)).filter(|_| self.color)
+        self.filter_color(Some(Color::Fixed(240)))
     }
     pub(crate) fn unimportant_color(&self) -> Option<Color> {
-        Some(Color::Fixed(249)).filter(|_| self.color)
+        self.filter_color(Some(Color::Fixed(249)))
     }
     pub(crate) fn note_color(&self) -> Option<Color> {
-        Some(Color::Fixed(115)).filter(|_| self.color)
+        self.filter_color(Some(Color::Fixed(115)))
     }
     pub(crate) fn filter_color(&self, color: Option<Color>) -> Option<Color> {
-        color.filter(|_| self.color)
+        color
+            .filter(|_| self.color)
+            .map(|color| match self.color_depth {
+                Some(depth) => draw::convert_color(color, depth),
+                None => color,
+            })
     }
 
-    // Find the character that should be drawn and the number of times it should be drawn for each char
-    pub(crate) fn char_width(&self, c: char, col: usize) -> (char, usize) {
+    // Find what should be drawn in place of each char of a line
+    pub(crate) fn char_display(&self, c: char, col: usize) -> CharDisplay {
         match c {
             '\t' => {
                 // Find the column that the tab should end at
                 let tab_end = (col / self.tab_width + 1) * self.tab_width;
-                (' ', tab_end - col)
+                if self.show_invisibles {
+                    CharDisplay::Padded('␉', tab_end - col)
+                } else {
+                    CharDisplay::Repeat(' ', tab_end - col)
+                }
             }
-            c if c.is_whitespace() => (' ', 1),
-            _ => (c, c.width().unwrap_or(1)),
+            ' ' => CharDisplay::Repeat(' ', 1),
+            c if self.escape_bidi && is_bidi_control(c) => CharDisplay::Escaped(c),
+            _ if self.show_invisibles => match c {
+                '\r' => CharDisplay::Single('␍', 1),
+                '\u{a0}' => CharDisplay::Single('·', 1),
+                c if c.is_whitespace() || c.is_control() || is_format_char(c) => {
+                    CharDisplay::Escaped(c)
+                }
+                c => CharDisplay::Single(c, c.width().unwrap_or(1)),
+            },
+            c if c.is_whitespace() => CharDisplay::Repeat(' ', 1),
+            c => CharDisplay::Single(c, c.width().unwrap_or(1)),
+        }
+    }
+
+    // Escape any characters in a message that could make it display misleadingly
+    pub(crate) fn escape_text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.escape_bidi && text.contains(is_bidi_control) {
+            let mut escaped = String::with_capacity(text.len());
+            for c in text.chars() {
+                if is_bidi_control(c) {
+                    escaped.push_str(&CharDisplay::Escaped(c).to_string());
+                } else {
+                    escaped.push(c);
+                }
+            }
+            Cow::Owned(escaped)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    // The part of a line's text that should be drawn
+    pub(crate) fn trim_line<'a>(&self, text: &'a str) -> &'a str {
+        if self.show_invisibles {
+            // Only hide the usual line endings, so that anything else at the end of the line can be seen
+            text.strip_suffix("\r\n")
+                .or_else(|| text.strip_suffix('\n'))
+                .unwrap_or(text)
+        } else {
+            text.trim_end()
         }
     }
 
@@ -183,6 +332,14 @@ impl Config {
             ansi_mode: AnsiMode::On,
             enumerate_notes: true,
             enumerate_helps: true,
+            enumerate_sections: true,
+            output_mode: OutputMode::Full,
+            short_labels: false,
+            hyperlinks: Hyperlinks::Off,
+            color_depth: None,
+            show_invisibles: false,
+            escape_bidi: true,
+            locale: Locale::new(),
         }
     }
 }
@@ -191,6 +348,31 @@ impl Default for Config {
         Self::new()
     }
 }
+/// Whether a character overrides the direction of, or isolates, the text that follows it.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Whether a character is in Unicode's `Cf` (format) category, which is made up of characters that are invisible but
+/// affect the characters around them, such as zero-width joiners and byte order marks.
+fn is_format_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{ad}'
+            | '\u{600}'..='\u{605}'
+            | '\u{61c}'
+            | '\u{6dd}'
+            | '\u{70f}'
+            | '\u{180e}'
+            | '\u{200b}'..='\u{200f}'
+            | '\u{202a}'..='\u{202e}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206f}'
+            | '\u{feff}'
+            | '\u{fff9}'..='\u{fffb}'
+    )
+}
+
 /// Possible character sets to use when rendering diagnostics.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum CharSet {
@@ -216,4 +398,198 @@ pub enum AnsiMode {
     Off,
     /// ANSI styling is disabled, diagnostics will have ANSI styling escape codes included.
     On,
+    /// ANSI styling is enabled only if the output stream is a terminal.
+    ///
+    /// The `NO_COLOR`, `CLICOLOR_FORCE`, `CLICOLOR` and `TERM` environment variables are respected: styling is
+    /// disabled if `NO_COLOR` is set, enabled if `CLICOLOR_FORCE` is set (to anything but `0`), and disabled if
+    /// `CLICOLOR=0` or `TERM=dumb`.
+    ///
+    /// The stream checked is `stderr`, unless the report is written with
+    /// [`Report::print`](crate::Report::print) or [`Report::write_for_stdout`](crate::Report::write_for_stdout).
+    Auto,
+}
+
+/// The format in which reports are written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// The full diagnostic, with source snippets, labels, notes and helps.
+    #[default]
+    Full,
+    /// A single `file:line:col: kind[code]: message` line per report, like `rustc --error-format=short` or gcc.
+    ///
+    /// See [`Config::with_short_labels`] to also list the location of each label.
+    Short,
+    /// [GitHub Actions workflow commands](https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions)
+    /// that annotate the location of each label, such as `::error file=src/main.rs,line=1,col=5::message`.
+    ///
+    /// File names are written as returned by [`Cache::display`](crate::Cache::display), and should be relative to
+    /// the root of the repository.
+    GitHub,
+    /// [GitLab Code Quality](https://docs.gitlab.com/ee/ci/testing/code_quality.html) issues for the location of each
+    /// label, written as one JSON object per line.
+    ///
+    /// GitLab expects a single JSON array of issues for each job, so the lines written for every report must be
+    /// joined with commas and wrapped in `[` and `]`. The `ariadne` command-line tool does this for you.
+    GitLab,
+    /// A `path:line:col: kind[code]: message (report message)` line for each label, which Vim's quickfix list
+    /// (`:make`) and Emacs's compilation mode (`M-x compile`) both understand without any extra configuration.
+    ///
+    /// The kind is written as `error`, `warning` or `note`, so that editors can tell them apart.
+    Quickfix,
+}
+
+/// Whether, and where to, the locations in a report's header should link using
+/// [OSC 8](https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feda) terminal hyperlinks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Hyperlinks {
+    /// Locations are plain text.
+    #[default]
+    Off,
+    /// Locations link to `file:///absolute/path#line`.
+    File,
+    /// Locations link to the given URL template, in which `{path}`, `{line}` and `{col}` are replaced with the
+    /// absolute path and one-indexed position of the location, e.g. `vscode://file{path}:{line}:{col}`.
+    ///
+    /// The path is written as in a `file://` URL: percent-encoded, separated by `/` and starting with `/`.
+    ///
+    /// The template is `&'static` for the same reason as the strings of a [`Locale`], so a template read at runtime,
+    /// such as from an editor setting, can be leaked once with [`String::leak`].
+    Template(&'static str),
+}
+
+/// The range of colors that a terminal can display.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// The 16 standard ANSI colors, such as [`Color::Red`] and [`Color::BrightBlue`].
+    Ansi16,
+    /// The 256 colors of the xterm palette, [`Color::Fixed`].
+    Ansi256,
+    /// Any 24-bit color, [`Color::Rgb`].
+    ///
+    /// The 16 standard colors are kept as they are, since terminals usually display them according to their theme.
+    TrueColor,
+}
+
+/// The words used for the fixed parts of reports, so that they can be translated.
+///
+/// The severities written by [`OutputMode::Short`], [`OutputMode::GitHub`], [`OutputMode::GitLab`] and
+/// [`OutputMode::Quickfix`] are not affected, since the tools that read them expect English.
+///
+/// ```
+/// use ariadne::{Config, Locale};
+///
+/// let config = Config::default().with_locale(
+///     Locale::new()
+///         .with_error("Fehler")
+///         .with_warning("Warnung")
+///         .with_advice("Hinweis")
+///         .with_help("Hilfe")
+///         .with_note("Anmerkung")
+///         .with_unknown_source("<unbekannt>"),
+/// );
+/// ```
+///
+/// Every string must be `&'static`, because a [`Config`] is [`Copy`] and can be built in `const` items, so it cannot
+/// own strings. A locale loaded at runtime, such as from a translation catalog, can leak its strings with
+/// [`String::leak`]. Do so once, when the catalog is loaded, rather than for each report, so that only one copy of each
+/// string is ever leaked:
+///
+/// ```
+/// use ariadne::{Config, Locale};
+///
+/// # let catalog = std::collections::HashMap::from([("error", "エラー".to_string())]);
+/// // e.g. `catalog: HashMap<&str, String>`, read from a file when the program starts
+/// let locale = match catalog.get("error") {
+///     Some(error) => Locale::new().with_error(error.clone().leak()),
+///     None => Locale::new(),
+/// };
+/// let config = Config::default().with_locale(locale);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Locale {
+    pub(crate) error: &'static str,
+    pub(crate) warning: &'static str,
+    pub(crate) advice: &'static str,
+    pub(crate) help: &'static str,
+    pub(crate) note: &'static str,
+    pub(crate) unknown_source: &'static str,
+    pub(crate) numbered: &'static str,
+}
+
+impl Locale {
+    /// Create a new, English locale.
+    pub const fn new() -> Self {
+        Self {
+            error: "Error",
+            warning: "Warning",
+            advice: "Advice",
+            help: "Help",
+            note: "Note",
+            unknown_source: "<unknown>",
+            numbered: "{name} {n}",
+        }
+    }
+
+    /// The name of [`ReportKind::Error`](crate::ReportKind::Error) reports.
+    ///
+    /// If unspecified, this defaults to `"Error"`.
+    pub const fn with_error(mut self, error: &'static str) -> Self {
+        self.error = error;
+        self
+    }
+    /// The name of [`ReportKind::Warning`](crate::ReportKind::Warning) reports.
+    ///
+    /// If unspecified, this defaults to `"Warning"`.
+    pub const fn with_warning(mut self, warning: &'static str) -> Self {
+        self.warning = warning;
+        self
+    }
+    /// The name of [`ReportKind::Advice`](crate::ReportKind::Advice) reports.
+    ///
+    /// If unspecified, this defaults to `"Advice"`.
+    pub const fn with_advice(mut self, advice: &'static str) -> Self {
+        self.advice = advice;
+        self
+    }
+    /// The prefix of helps.
+    ///
+    /// If unspecified, this defaults to `"Help"`.
+    pub const fn with_help(mut self, help: &'static str) -> Self {
+        self.help = help;
+        self
+    }
+    /// The prefix of notes.
+    ///
+    /// If unspecified, this defaults to `"Note"`.
+    pub const fn with_note(mut self, note: &'static str) -> Self {
+        self.note = note;
+        self
+    }
+    /// The name of sources that their [`Cache`](crate::Cache) cannot display.
+    ///
+    /// If unspecified, this defaults to `"<unknown>"`.
+    pub const fn with_unknown_source(mut self, unknown_source: &'static str) -> Self {
+        self.unknown_source = unknown_source;
+        self
+    }
+    /// The pattern used for the prefixes of enumerated notes and helps, in which `{name}` is replaced with the
+    /// prefix and `{n}` with the number.
+    ///
+    /// If unspecified, this defaults to `"{name} {n}"`, giving prefixes such as `Note 1`.
+    pub const fn with_numbered(mut self, numbered: &'static str) -> Self {
+        self.numbered = numbered;
+        self
+    }
+
+    pub(crate) fn enumerate(&self, name: &str, n: usize) -> String {
+        self.numbered
+            .replace("{name}", name)
+            .replace("{n}", &n.to_string())
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -1,4 +1,10 @@
 use super::*;
+use std::{
+    env,
+    ffi::OsString,
+    hash::{Hash, Hasher},
+    io::IsTerminal,
+};
 use yansi::Paint;
 
 #[allow(dead_code)]
@@ -245,6 +251,86 @@ impl<T: fmt::Display> fmt::Display for Background<T> {
     }
 }
 
+/// What is drawn in place of a character of the source.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum CharDisplay {
+    /// A character repeated to fill the given width, as for whitespace.
+    Repeat(char, usize),
+    /// A single character that takes up the given width.
+    Single(char, usize),
+    /// A single character, padded with spaces to the given width.
+    Padded(char, usize),
+    /// The code point of an invisible character, such as `<U+200B>`.
+    Escaped(char),
+}
+
+impl CharDisplay {
+    /// The number of columns taken up.
+    pub(crate) fn width(&self) -> usize {
+        match self {
+            Self::Repeat(_, width) | Self::Single(_, width) | Self::Padded(_, width) => *width,
+            // `<U+` and `>` around at least 4 hex digits
+            Self::Escaped(c) => {
+                4 + ((32 - (*c as u32).leading_zeros()).div_ceil(4) as usize).max(4)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CharDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Repeat(c, width) => write!(f, "{}", Rept(*c, *width)),
+            Self::Single(c, _) => write!(f, "{c}"),
+            Self::Padded(c, width) => write!(f, "{c}{}", Rept(' ', width.saturating_sub(1))),
+            Self::Escaped(c) => write!(f, "<U+{:04X}>", *c as u32),
+        }
+    }
+}
+
+/// Text from the source, highlighted as part of a label.
+pub(crate) struct Highlighted<T>(T, Option<Color>, Option<LabelHighlight>);
+
+impl<T: fmt::Display> Highlighted<T> {
+    /// Highlight text in the given color, if any. The highlight is only drawn if `styled` is set and color is enabled
+    /// for the stream, but attributes such as bold are drawn even without a color.
+    pub(crate) fn new(
+        text: T,
+        color: Option<Color>,
+        highlight: LabelHighlight,
+        styled: bool,
+        stream: StreamType,
+    ) -> Self {
+        let styled = styled && T::color_enabled_for(stream);
+        Self(
+            text,
+            color.filter(|_| styled),
+            Some(highlight).filter(|_| styled),
+        )
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Highlighted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(highlight) = self.2 else {
+            return write!(f, "{}", self.0);
+        };
+        let text = Paint::new(&self.0);
+        let fg = match self.1 {
+            Some(col) => text.fg(col),
+            None => text,
+        };
+        match (highlight, self.1) {
+            (LabelHighlight::Foreground, _) => write!(f, "{}", fg),
+            (LabelHighlight::Background, Some(col)) => write!(f, "{}", text.bg(col)),
+            // Without a color to draw the background in, swap the terminal's own colors instead.
+            (LabelHighlight::Background, None) => write!(f, "{}", text.invert()),
+            (LabelHighlight::Bold, _) => write!(f, "{}", fg.bold()),
+            (LabelHighlight::Underline, _) => write!(f, "{}", fg.underline()),
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum WrappedWriter<W: Write> {
     Strip(strip_ansi_escapes::Writer<W>),
@@ -252,14 +338,43 @@ pub(crate) enum WrappedWriter<W: Write> {
 }
 
 impl<W: Write> WrappedWriter<W> {
-    pub(crate) fn new(w: W, config: &Config) -> Self {
-        match config.ansi_mode {
-            AnsiMode::Off => Self::Strip(strip_ansi_escapes::Writer::new(w)),
-            AnsiMode::On => Self::Keep(w),
+    pub(crate) fn new(w: W, config: &Config, s: StreamType) -> Self {
+        let keep = match config.ansi_mode {
+            AnsiMode::Off => false,
+            AnsiMode::On => true,
+            AnsiMode::Auto => ansi_supported(
+                |name| env::var_os(name),
+                match s {
+                    StreamType::Stdout => io::stdout().is_terminal(),
+                    StreamType::Stderr => io::stderr().is_terminal(),
+                },
+            ),
+        };
+        if keep {
+            Self::Keep(w)
+        } else {
+            Self::Strip(strip_ansi_escapes::Writer::new(w))
         }
     }
 }
 
+/// Decide whether ANSI styling should be written, following the conventions of <https://no-color.org> and
+/// <https://bixense.com/clicolors>.
+fn ansi_supported(var: impl Fn(&str) -> Option<OsString>, is_terminal: bool) -> bool {
+    let set = |name| var(name).filter(|value| !value.is_empty());
+    if set("NO_COLOR").is_some() {
+        false
+    } else if set("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+        true
+    } else if set("CLICOLOR").is_some_and(|value| value == "0")
+        || set("TERM").is_some_and(|value| value == "dumb")
+    {
+        false
+    } else {
+        is_terminal
+    }
+}
+
 impl<W: Write> Write for WrappedWriter<W> {
     fn flush(&mut self) -> io::Result<()> {
         match self {
@@ -276,10 +391,119 @@ impl<W: Write> Write for WrappedWriter<W> {
     }
 }
 
-/// A type that can generate distinct 8-bit colors.
+/// The RGB values of the 16 standard colors, as displayed by xterm.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::BrightBlack, (127, 127, 127)),
+    (Color::BrightRed, (255, 0, 0)),
+    (Color::BrightGreen, (0, 255, 0)),
+    (Color::BrightYellow, (255, 255, 0)),
+    (Color::BrightBlue, (92, 92, 255)),
+    (Color::BrightMagenta, (255, 0, 255)),
+    (Color::BrightCyan, (0, 255, 255)),
+    (Color::BrightWhite, (255, 255, 255)),
+];
+
+/// The intensities of each channel in the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Convert a color to the closest one that can be displayed at the given depth.
+pub(crate) fn convert_color(color: Color, depth: ColorDepth) -> Color {
+    match (depth, color) {
+        (_, Color::Primary) => color,
+        (ColorDepth::Ansi16, Color::Fixed(n)) => nearest_ansi16(fixed_to_rgb(n)),
+        (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi16((r, g, b)),
+        (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Fixed(nearest_fixed((r, g, b))),
+        (ColorDepth::TrueColor, Color::Fixed(n)) => {
+            let (r, g, b) = fixed_to_rgb(n);
+            Color::Rgb(r, g, b)
+        }
+        _ => color,
+    }
+}
+
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16[n as usize].1,
+        16..=231 => {
+            let i = n as usize - 16;
+            (
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[i / 6 % 6],
+                CUBE_LEVELS[i % 6],
+            )
+        }
+        _ => {
+            let level = 8 + (n - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// The difference between two colors, weighted to roughly match how sensitive the eye is to each channel.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    2 * d(r1, r2) + 4 * d(g1, g2) + 3 * d(b1, b2)
+}
+
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+fn nearest_fixed((r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs())
+            .unwrap() as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let gray_level = ((r as u32 + g as u32 + b as u32) / 3).clamp(8, 238);
+    let gray = 232 + ((gray_level - 8 + 5) / 10).min(23) as u8;
+    if distance((r, g, b), fixed_to_rgb(cube)) <= distance((r, g, b), fixed_to_rgb(gray)) {
+        cube
+    } else {
+        gray
+    }
+}
+
+/// An implementation of the FNV-1a hash, which, unlike the hashers of the standard library, gives the same result in
+/// every run of a program.
+pub(crate) struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// A type that can generate distinct 8-bit or 24-bit colors.
 pub struct ColorGenerator {
     state: [u16; 3],
     min_brightness: f32,
+    rgb: bool,
 }
 
 impl Default for ColorGenerator {
@@ -296,9 +520,24 @@ impl ColorGenerator {
         Self {
             state,
             min_brightness: min_brightness.max(0.0).min(1.0),
+            rgb: false,
         }
     }
 
+    /// Create a new [`ColorGenerator`] with the default state that generates 24-bit [`Color::Rgb`] colors.
+    ///
+    /// Successive colors are spread evenly around the color wheel, so they remain distinct from each other for much
+    /// longer than those of the 256-color palette.
+    pub const fn new_rgb() -> Self {
+        Self::new().with_rgb(true)
+    }
+
+    /// Should this generator produce 24-bit [`Color::Rgb`] colors rather than 8-bit [`Color::Fixed`] colors?
+    pub const fn with_rgb(mut self, rgb: bool) -> Self {
+        self.rgb = rgb;
+        self
+    }
+
     /// Create a new [`ColorGenerator`] with the default state.
     pub const fn new() -> Self {
         Self::from_state([30000, 15000, 35000], 0.5)
@@ -314,6 +553,9 @@ impl ColorGenerator {
             self.state[i] = (self.state[i] as usize).wrapping_add(40503 * (i * 4 + 1130)) as u16;
             i += 1;
         }
+        if self.rgb {
+            return self.next_rgb();
+        }
         Color::Fixed(
             16 + ((self.state[2] as f32 / 65535.0 * (1.0 - self.min_brightness)
                 + self.min_brightness)
@@ -326,12 +568,59 @@ impl ColorGenerator {
                     * 180.0) as u8,
         )
     }
+
+    /// Pick a color for the given key, such as the name of a variable.
+    ///
+    /// The same key always gets the same color, no matter how many colors have been generated so far, so labels that
+    /// refer to the same thing share a color even when they are in separate reports. Colors are picked from the first
+    /// 64 colors generated by [`ColorGenerator::new`], with this generator's brightness and RGB mode.
+    pub fn color_for<K: Hash + ?Sized>(&self, key: &K) -> Color {
+        let mut hasher = StableHasher::default();
+        key.hash(&mut hasher);
+        let mut gen = Self::from_state(Self::new().state, self.min_brightness).with_rgb(self.rgb);
+        for _ in 0..hasher.finish() % 64 {
+            gen.next();
+        }
+        gen.next()
+    }
+
+    const fn next_rgb(&self) -> Color {
+        // The first state steps by 24262 / 65536, about 0.37 of a turn, which keeps consecutive hues far apart
+        let hue = self.state[0] as f32 / 65536.0 * 6.0;
+        let saturation = 0.55 + self.state[1] as f32 / 65535.0 * 0.3;
+        let value = self.min_brightness + (1.0 - self.min_brightness) * 0.85;
+
+        let sector = hue as u8;
+        let f = hue - sector as f32;
+        let (p, q, t) = (
+            value * (1.0 - saturation),
+            value * (1.0 - saturation * f),
+            value * (1.0 - saturation * (1.0 - f)),
+        );
+        let (r, g, b) = match sector {
+            0 => (value, t, p),
+            1 => (q, value, p),
+            2 => (p, value, t),
+            3 => (p, q, value),
+            4 => (t, p, value),
+            _ => (value, p, q),
+        };
+        Color::Rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn escaped_width() {
+        for c in ['\u{7}', '\u{200b}', '\u{e0001}', '\u{10ffff}'] {
+            let escaped = CharDisplay::Escaped(c);
+            assert_eq!(escaped.width(), escaped.to_string().len());
+        }
+    }
+
     #[test]
     fn const_colors() {
         const COLORS: [Color; 3] = {
@@ -342,4 +631,95 @@ mod tests {
         assert_ne!(COLORS[1], COLORS[2]);
         assert_ne!(COLORS[2], COLORS[0]);
     }
+
+    #[test]
+    fn rgb_colors() {
+        const COLORS: [Color; 3] = {
+            let mut gen = ColorGenerator::new_rgb();
+            [gen.next(), gen.next(), gen.next()]
+        };
+        for color in COLORS {
+            assert!(matches!(color, Color::Rgb(..)));
+        }
+        assert_ne!(COLORS[0], COLORS[1]);
+        assert_ne!(COLORS[1], COLORS[2]);
+        assert_ne!(COLORS[2], COLORS[0]);
+    }
+
+    #[test]
+    fn key_colors() {
+        let mut gen = ColorGenerator::new();
+        let a = gen.color_for("a");
+        gen.next();
+        gen.next();
+        assert_eq!(gen.color_for("a"), a);
+        assert_eq!(ColorGenerator::new().color_for("a"), a);
+        assert_ne!(gen.color_for("b"), a);
+        assert!(matches!(
+            ColorGenerator::new_rgb().color_for(&42u32),
+            Color::Rgb(..)
+        ));
+    }
+
+    #[test]
+    fn color_depth() {
+        use ColorDepth::*;
+
+        // Downsampling
+        assert_eq!(convert_color(Color::Fixed(196), Ansi16), Color::BrightRed);
+        assert_eq!(convert_color(Color::Fixed(246), Ansi16), Color::BrightBlack);
+        assert_eq!(convert_color(Color::Rgb(0, 0, 200), Ansi16), Color::Blue);
+        assert_eq!(
+            convert_color(Color::Rgb(255, 0, 0), Ansi256),
+            Color::Fixed(196)
+        );
+        assert_eq!(
+            convert_color(Color::Rgb(128, 128, 128), Ansi256),
+            Color::Fixed(244)
+        );
+        // Upsampling
+        assert_eq!(
+            convert_color(Color::Fixed(196), TrueColor),
+            Color::Rgb(255, 0, 0)
+        );
+        assert_eq!(
+            convert_color(Color::Fixed(246), TrueColor),
+            Color::Rgb(148, 148, 148)
+        );
+        // Colors that already fit are left alone
+        assert_eq!(convert_color(Color::Red, Ansi16), Color::Red);
+        assert_eq!(convert_color(Color::Red, TrueColor), Color::Red);
+        assert_eq!(convert_color(Color::Fixed(147), Ansi256), Color::Fixed(147));
+        assert_eq!(convert_color(Color::Primary, Ansi16), Color::Primary);
+
+        for n in 16..=255 {
+            assert_eq!(nearest_fixed(fixed_to_rgb(n)), n);
+        }
+    }
+
+    #[test]
+    fn ansi_detection() {
+        let detect = |vars: &[(&str, &str)], is_terminal| {
+            ansi_supported(
+                |name| {
+                    vars.iter()
+                        .find(|(var, _)| *var == name)
+                        .map(|(_, value)| value.into())
+                },
+                is_terminal,
+            )
+        };
+
+        assert!(detect(&[], true));
+        assert!(!detect(&[], false));
+        assert!(!detect(&[("NO_COLOR", "1")], true));
+        assert!(detect(&[("NO_COLOR", "")], true));
+        assert!(!detect(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")], true));
+        assert!(detect(&[("CLICOLOR_FORCE", "1")], false));
+        assert!(!detect(&[("CLICOLOR_FORCE", "0")], false));
+        assert!(detect(&[("CLICOLOR_FORCE", "1"), ("TERM", "dumb")], false));
+        assert!(!detect(&[("CLICOLOR", "0")], true));
+        assert!(detect(&[("CLICOLOR", "1")], true));
+        assert!(!detect(&[("TERM", "dumb")], true));
+    }
 }
@@ -11,6 +11,8 @@ pub(crate) struct LabelDisplay {
     pub color: Option<Color>,
     pub order: i32,
     pub priority: i32,
+    pub highlight: LabelHighlight,
+    pub notes: Vec<String>,
 }
 
 /// A type that represents a labelled section of source code.
@@ -37,6 +39,8 @@ impl<S: Span> Label<S> {
                 color: None,
                 order: 0,
                 priority: 0,
+                highlight: LabelHighlight::Foreground,
+                notes: Vec::new(),
             },
         }
     }
@@ -47,6 +51,15 @@ impl<S: Span> Label<S> {
         self
     }
 
+    /// Add a note to this label, which is drawn beneath the label's message.
+    ///
+    /// Labels without a message have nothing to draw their notes beneath, so their notes are listed after the snippet
+    /// of the source that contains them instead.
+    pub fn with_note<N: ToString>(mut self, note: N) -> Self {
+        self.display_info.notes.push(note.to_string());
+        self
+    }
+
     /// Give this label a highlight colour.
     pub fn with_color(mut self, color: Color) -> Self {
         self.display_info.color = Some(color);
@@ -81,6 +94,36 @@ impl<S: Span> Label<S> {
         self.display_info.priority = priority;
         self
     }
+
+    /// Specify how the source text covered by this label should be highlighted.
+    ///
+    /// This is particularly useful when underlines are disabled with
+    /// [`Config::with_underlines`](crate::Config::with_underlines), since the highlight is then the only indication of
+    /// where the label is within a line.
+    ///
+    /// If unspecified, labels default to [`LabelHighlight::Foreground`].
+    pub fn with_highlight(mut self, highlight: LabelHighlight) -> Self {
+        self.display_info.highlight = highlight;
+        self
+    }
+}
+
+/// The way in which the source text covered by a label is highlighted, using the label's color.
+///
+/// Labels without a color are still drawn in bold or underlined, and their background is drawn by swapping the
+/// terminal's foreground and background colors. Nothing is highlighted if color is disabled in the
+/// [`Config`](crate::Config).
+#[derive(Copy, Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub enum LabelHighlight {
+    /// The text is drawn in the label's color.
+    #[default]
+    Foreground,
+    /// The text is drawn on a background of the label's color.
+    Background,
+    /// The text is drawn in the label's color, in bold.
+    Bold,
+    /// The text is drawn in the label's color, underlined.
+    Underline,
 }
 
 /// The attachment point of inline label arrows
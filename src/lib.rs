@@ -8,6 +8,8 @@ mod display;
 mod draw;
 mod label;
 mod report;
+#[cfg(feature = "rustc")]
+pub mod rustc;
 mod source;
 mod span;
 pub use crate::label::*;
@@ -17,10 +19,16 @@ pub use crate::report::{Report, ReportKind};
 pub use crate::span::*;
 pub use crate::{
     draw::{ColorGenerator, Fmt},
-    source::{sources, Cache, FileCache, FnCache, Line, Source},
+    source::{
+        sources, Cache, Encoding, FallbackCache, FileCache, FnCache, Line, LineEnding, LineEndings,
+        Location, MapIdCache, OverlayCache, PathDisplay, SharedCache, Source,
+    },
 };
 pub use config::*;
 
+#[cfg(feature = "mmap")]
+pub use crate::source::{MappedText, MmapFileCache};
+
 pub use yansi::Color;
 
 #[cfg(any(feature = "concolor", doc))]
@@ -1,23 +1,35 @@
 use core::fmt;
 
 use crate::{
-    report::{Report, ReportStyle},
-    Config, Label, Span,
+    report::{Report, ReportStyle, Section, SectionKind},
+    Color, Config, Label, Span,
 };
 
 /// A type used to build a [`Report`].
 #[must_use = "call `.finish()` to obtain a `Report`"]
 pub struct ReportBuilder<S: Span, K: ReportStyle> {
     pub(crate) kind: K,
+    pub(crate) code: Option<String>,
     pub(crate) msg: Option<String>,
-    pub(crate) notes: Vec<String>,
-    pub(crate) help: Vec<String>,
+    /// Helps, notes and custom sections, in the order in which they were first added to.
+    pub(crate) sections: Vec<Section>,
     pub(crate) span: S,
     pub(crate) labels: Vec<Label<S>>,
     pub(crate) config: Config,
 }
 
 impl<S: Span, K: ReportStyle> ReportBuilder<S, K> {
+    /// Give this report a numerical code that may be used to more precisely look up the error in documentation.
+    pub fn with_code<C: fmt::Display>(mut self, code: C) -> Self {
+        self.code = Some(format!("{code}"));
+        self
+    }
+
+    /// Set the code of this report, or remove it.
+    pub fn set_code<C: fmt::Display>(&mut self, code: Option<C>) {
+        self.code = code.map(|c| format!("{c}"));
+    }
+
     /// Set the message of this report.
     pub fn set_message<M: ToString>(&mut self, msg: M) {
         self.msg = Some(msg.to_string());
@@ -31,12 +43,12 @@ impl<S: Span, K: ReportStyle> ReportBuilder<S, K> {
 
     /// Set the note of this report.
     pub fn set_note<N: ToString>(&mut self, note: N) {
-        self.notes = vec![note.to_string()];
+        self.section(SectionKind::Note).items = vec![note.to_string()];
     }
 
     /// Adds a note to this report.
     pub fn add_note<N: ToString>(&mut self, note: N) {
-        self.notes.push(note.to_string());
+        self.section(SectionKind::Note).items.push(note.to_string());
     }
 
     /// Removes all notes in this report.
@@ -54,12 +66,12 @@ impl<S: Span, K: ReportStyle> ReportBuilder<S, K> {
 
     /// Set the help message of this report.
     pub fn set_help<N: ToString>(&mut self, note: N) {
-        self.help = vec![note.to_string()];
+        self.section(SectionKind::Help).items = vec![note.to_string()];
     }
 
     /// Add a help message to this report.
     pub fn add_help<N: ToString>(&mut self, note: N) {
-        self.help.push(note.to_string());
+        self.section(SectionKind::Help).items.push(note.to_string());
     }
 
     /// Set the help messages of this report.
@@ -75,6 +87,60 @@ impl<S: Span, K: ReportStyle> ReportBuilder<S, K> {
         self
     }
 
+    /// Add an item to the footer section with the given name, such as `"Caused by"` or `"See also"`.
+    ///
+    /// Sections are drawn like notes and helps, and all of them are drawn in the order in which they were first added
+    /// to, except that helps always come before notes. They are drawn in the same color as notes unless
+    /// [`ReportBuilder::with_section_color`] is used.
+    pub fn add_section<N: ToString, T: ToString>(&mut self, name: N, item: T) {
+        self.section(SectionKind::Custom(name.to_string()))
+            .items
+            .push(item.to_string());
+    }
+
+    /// Add an item to the footer section with the given name, such as `"Caused by"` or `"See also"`.
+    ///
+    /// See [`ReportBuilder::add_section`].
+    pub fn with_section<N: ToString, T: ToString>(mut self, name: N, item: T) -> Self {
+        self.add_section(name, item);
+        self
+    }
+
+    /// Set the color of the prefix of the footer section with the given name.
+    pub fn with_section_color<N: ToString>(mut self, name: N, color: Color) -> Self {
+        self.section(SectionKind::Custom(name.to_string())).color = Some(color);
+        self
+    }
+
+    fn section(&mut self, kind: SectionKind) -> &mut Section {
+        let find = |kind: &SectionKind| {
+            self.sections
+                .iter()
+                .position(|section| &section.kind == kind)
+        };
+        let idx = match find(&kind) {
+            Some(idx) => idx,
+            None => {
+                // Helps have always been drawn before notes
+                let idx = match kind {
+                    SectionKind::Help => find(&SectionKind::Note),
+                    _ => None,
+                }
+                .unwrap_or(self.sections.len());
+                self.sections.insert(
+                    idx,
+                    Section {
+                        kind,
+                        color: None,
+                        items: Vec::new(),
+                    },
+                );
+                idx
+            }
+        };
+        &mut self.sections[idx]
+    }
+
     /// Add a label to the report.
     pub fn add_label(&mut self, label: Label<S>) {
         self.add_labels(std::iter::once(label));
@@ -108,12 +174,16 @@ impl<S: Span, K: ReportStyle> ReportBuilder<S, K> {
     }
 
     /// Finish building the [`Report`].
-    pub fn finish(self) -> Report<S, K> {
+    pub fn finish(mut self) -> Report<S, K> {
+        // The config may have changed since the labels were added
+        for label in &mut self.labels {
+            label.display_info.color = self.config.filter_color(label.display_info.color);
+        }
         Report {
             kind: self.kind,
+            code: self.code,
             msg: self.msg,
-            notes: self.notes,
-            help: self.help,
+            sections: self.sections,
             span: self.span,
             labels: self.labels,
             config: self.config,
@@ -125,9 +195,9 @@ impl<S: Span, K: ReportStyle> fmt::Debug for ReportBuilder<S, K> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ReportBuilder")
             .field("kind", &self.kind)
+            .field("code", &self.code)
             .field("msg", &self.msg)
-            .field("notes", &self.notes)
-            .field("help", &self.help)
+            .field("sections", &self.sections)
             .field("config", &self.config)
             .finish()
     }
@@ -0,0 +1,213 @@
+use std::{hash::Hasher, io};
+
+use super::write::{span_position, SpanPosition};
+use crate::{draw::StableHasher, Cache, Report, ReportStyle, Span, Write};
+
+/// How severe a report is, in the terms understood by CI annotation formats.
+#[derive(Copy, Clone)]
+pub(super) enum Severity {
+    Error,
+    Warning,
+    Notice,
+}
+
+/// A location annotated by a report, along with the text describing it.
+pub(super) struct Annotation<'a, Id: ?Sized> {
+    pub(super) src_id: &'a Id,
+    pub(super) position: Option<SpanPosition>,
+    pub(super) msg: Option<String>,
+}
+
+impl<S: Span, K: ReportStyle> Report<S, K> {
+    pub(super) fn severity(&self) -> Severity {
+        match self.kind.to_string().to_lowercase().as_str() {
+            "error" => Severity::Error,
+            "warning" => Severity::Warning,
+            _ => Severity::Notice,
+        }
+    }
+
+    /// Strip any styling from text and escape it, for output that is read by other tools rather than drawn.
+    pub(super) fn plain_text(&self, text: &str) -> String {
+        self.config.escape_text(&plain(text)).into_owned()
+    }
+
+    /// One annotation per label, or a single annotation at the report's own span if it has no labels.
+    pub(super) fn annotations<C: Cache<S::SourceId>>(
+        &self,
+        cache: &mut C,
+    ) -> Vec<Annotation<'_, S::SourceId>> {
+        if self.labels.is_empty() {
+            let span = self.span.start()..self.span.end();
+            vec![Annotation {
+                src_id: self.span.source(),
+                position: span_position(cache, self.span.source(), span, &self.config),
+                msg: None,
+            }]
+        } else {
+            self.labels
+                .iter()
+                .map(|label| Annotation {
+                    src_id: label.span.source(),
+                    position: span_position(
+                        cache,
+                        label.span.source(),
+                        label.span.start()..label.span.end(),
+                        &self.config,
+                    ),
+                    msg: label
+                        .display_info
+                        .msg
+                        .as_deref()
+                        .map(|msg| self.plain_text(msg)),
+                })
+                .collect()
+        }
+    }
+
+    /// Write this diagnostic as GitHub Actions workflow commands, one per label.
+    pub(super) fn write_github<C: Cache<S::SourceId>, W: Write>(
+        &self,
+        cache: &mut C,
+        w: &mut W,
+    ) -> io::Result<()> {
+        let command = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Notice => "notice",
+        };
+        let title = self.msg.as_deref().map(|msg| self.plain_text(msg));
+
+        for annotation in self.annotations(cache) {
+            let mut properties = Vec::new();
+            if let Some(position) = &annotation.position {
+                properties.push(format!("file={}", escape_property(&position.src_name)));
+                properties.push(format!("line={}", position.start.0));
+                properties.push(format!("endLine={}", position.end.0));
+                // Columns only make sense for annotations that stay on a single line
+                if position.start.0 == position.end.0 {
+                    properties.push(format!("col={}", position.start.1));
+                    properties.push(format!("endColumn={}", position.end.1));
+                }
+            }
+            if let Some(title) = &title {
+                properties.push(format!("title={}", escape_property(title)));
+            }
+
+            let mut msg = annotation
+                .msg
+                .or_else(|| title.clone())
+                .unwrap_or_else(|| self.plain_text(&self.kind.localised_name(&self.config)));
+            for section in &self.sections {
+                for item in &section.items {
+                    msg.push_str(&format!(
+                        "\n{}: {}",
+                        self.plain_text(section.name(&self.config)),
+                        self.plain_text(item)
+                    ));
+                }
+            }
+
+            writeln!(
+                w,
+                "::{command} {}::{}",
+                properties.join(","),
+                escape_data(&msg)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write this diagnostic as GitLab Code Quality issues, one JSON object per label, each on its own line.
+    pub(super) fn write_gitlab<C: Cache<S::SourceId>, W: Write>(
+        &self,
+        cache: &mut C,
+        w: &mut W,
+    ) -> io::Result<()> {
+        let severity = match self.severity() {
+            Severity::Error => "major",
+            Severity::Warning => "minor",
+            Severity::Notice => "info",
+        };
+        let check_name = self.plain_text(
+            &self
+                .code
+                .clone()
+                .unwrap_or_else(|| self.kind.to_string().to_lowercase()),
+        );
+        let msg = self.msg.as_deref().map(|msg| self.plain_text(msg));
+
+        for annotation in self.annotations(cache) {
+            let description = match (&msg, &annotation.msg) {
+                (Some(msg), Some(label_msg)) => format!("{msg}: {label_msg}"),
+                (Some(msg), None) => msg.clone(),
+                (None, Some(label_msg)) => label_msg.clone(),
+                (None, None) => self.plain_text(&self.kind.localised_name(&self.config)),
+            };
+            // GitLab requires every issue to have a location, so fall back to a placeholder if the source is missing
+            let (path, start, end) = match &annotation.position {
+                Some(position) => (position.src_name.as_str(), position.start, position.end),
+                None => ("", (1, 1), (1, 1)),
+            };
+            let mut fingerprint = StableHasher::default();
+            fingerprint.write(
+                format!(
+                    "{check_name}\0{path}\0{}\0{}\0{description}",
+                    start.0, start.1
+                )
+                .as_bytes(),
+            );
+            let fingerprint = fingerprint.finish();
+
+            writeln!(
+                w,
+                "{{\"description\":{},\"check_name\":{},\"fingerprint\":\"{fingerprint:016x}\",\"severity\":\"{severity}\",\
+                \"location\":{{\"path\":{},\"positions\":{{\"begin\":{{\"line\":{},\"column\":{}}},\
+                \"end\":{{\"line\":{},\"column\":{}}}}}}}}}",
+                json_string(&description),
+                json_string(&check_name),
+                json_string(path),
+                start.0,
+                start.1,
+                end.0,
+                end.1,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Strip any ANSI styling (such as that added with [`Fmt`](crate::Fmt)) from text that is not displayed in a terminal.
+fn plain(text: &str) -> String {
+    strip_ansi_escapes::strip_str(text)
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
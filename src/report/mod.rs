@@ -5,6 +5,9 @@ use crate::{
     *,
 };
 pub(crate) mod builder;
+mod ci;
+mod quickfix;
+mod short;
 pub(crate) mod style;
 #[cfg(test)]
 mod tests;
@@ -12,13 +15,13 @@ pub(crate) mod write;
 /// A type representing a diagnostic that is ready to be written to output.
 #[must_use = "call `.print()` or `.eprint()` to print the report"]
 pub struct Report<S: Span = Range<usize>, K: ReportStyle = ReportKind> {
-    kind: K,
-    msg: Option<String>,
-    notes: Vec<String>,
-    help: Vec<String>,
-    span: S,
-    labels: Vec<Label<S>>,
-    config: Config,
+    pub(crate) kind: K,
+    pub(crate) code: Option<String>,
+    pub(crate) msg: Option<String>,
+    pub(crate) sections: Vec<Section>,
+    pub(crate) span: S,
+    pub(crate) labels: Vec<Label<S>>,
+    pub(crate) config: Config,
 }
 
 impl<S: Span, K: ReportStyle> Report<S, K> {
@@ -28,9 +31,9 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
     pub fn build(kind: K, span: S) -> ReportBuilder<S, K> {
         ReportBuilder {
             kind,
+            code: None,
             msg: None,
-            notes: vec![],
-            help: vec![],
+            sections: Vec::new(),
             span,
             labels: Vec::new(),
             config: Config::default(),
@@ -54,14 +57,49 @@ impl<S: Span, K: ReportStyle> fmt::Debug for Report<S, K> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Report")
             .field("kind", &self.kind)
+            .field("code", &self.code)
             .field("msg", &self.msg)
-            .field("notes", &self.notes)
-            .field("help", &self.help)
+            .field("sections", &self.sections)
             .field("config", &self.config)
             .finish_non_exhaustive()
     }
 }
 
+/// A footer section of a report: its helps, its notes, or a custom section such as "Caused by".
+#[derive(Clone, Debug)]
+pub(crate) struct Section {
+    pub(crate) kind: SectionKind,
+    pub(crate) color: Option<Color>,
+    pub(crate) items: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SectionKind {
+    Help,
+    Note,
+    Custom(String),
+}
+
+impl Section {
+    /// The prefix of each item in this section.
+    pub(crate) fn name<'a>(&'a self, config: &'a Config) -> &'a str {
+        match &self.kind {
+            SectionKind::Help => config.locale.help,
+            SectionKind::Note => config.locale.note,
+            SectionKind::Custom(name) => name,
+        }
+    }
+
+    /// Should the items of this section be numbered when there is more than one?
+    pub(crate) fn enumerate(&self, config: &Config) -> bool {
+        match self.kind {
+            SectionKind::Help => config.enumerate_helps,
+            SectionKind::Note => config.enumerate_notes,
+            SectionKind::Custom(_) => config.enumerate_sections,
+        }
+    }
+}
+
 /**
  * A Type for basic error handeling in all common cases.
  */
@@ -99,7 +137,16 @@ impl ReportStyle for ReportKind {
             ReportKind::Error => config.error_color(),
             ReportKind::Warning => config.warning_color(),
             ReportKind::Advice => config.advice_color(),
-            ReportKind::Custom(_, color) => Some(*color).filter(|_| config.color),
+            ReportKind::Custom(_, color) => config.filter_color(Some(*color)),
+        }
+    }
+
+    fn localised_name(&self, config: &Config) -> String {
+        match self {
+            ReportKind::Error => config.locale.error.to_string(),
+            ReportKind::Warning => config.locale.warning.to_string(),
+            ReportKind::Advice => config.locale.advice.to_string(),
+            ReportKind::Custom(name, _) => name.to_string(),
         }
     }
 }
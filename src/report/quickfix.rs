@@ -0,0 +1,54 @@
+use std::io;
+
+use super::{ci::Severity, write::display_name};
+use crate::{Cache, Report, ReportStyle, Show, Span, Write};
+
+impl<S: Span, K: ReportStyle> Report<S, K> {
+    /// Write this diagnostic as `path:line:col: kind[code]: message` entries, one per label, in the form parsed by
+    /// Vim's quickfix list and Emacs's compilation mode.
+    pub(super) fn write_quickfix<C: Cache<S::SourceId>, W: Write>(
+        &self,
+        cache: &mut C,
+        w: &mut W,
+    ) -> io::Result<()> {
+        // Both editors recognise these words, and Emacs uses them to tell errors, warnings and infos apart
+        let kind = match self.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Notice => "note",
+        };
+        let code = self
+            .code
+            .as_ref()
+            .map(|c| format!("[{}]", self.plain_text(c)));
+        let msg = self.msg.as_deref().map(|msg| self.plain_text(msg));
+
+        for annotation in self.annotations(cache) {
+            match &annotation.position {
+                Some(position) => write!(
+                    w,
+                    "{}:{}:{}: ",
+                    position.src_name, position.start.0, position.start.1
+                )?,
+                None => write!(
+                    w,
+                    "{}: ",
+                    display_name(cache, annotation.src_id, &self.config)
+                )?,
+            }
+            write!(w, "{kind}{}: ", Show(code.as_ref()))?;
+            match (&annotation.msg, &msg) {
+                (Some(label_msg), Some(msg)) => write!(w, "{label_msg} ({msg})")?,
+                (Some(msg), None) | (None, Some(msg)) => write!(w, "{msg}")?,
+                (None, None) => write!(
+                    w,
+                    "{}",
+                    self.plain_text(&self.kind.localised_name(&self.config))
+                )?,
+            }
+            writeln!(w)?;
+        }
+
+        Ok(())
+    }
+}
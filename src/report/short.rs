@@ -0,0 +1,78 @@
+use std::io;
+
+use super::write::{char_location, display_name, fetch_source, Hyperlink, Loc};
+use crate::{
+    draw::{StreamAwareFmt, StreamType, WrappedWriter},
+    Cache, Report, ReportStyle, Show, Span, Write,
+};
+
+impl<S: Span, K: ReportStyle> Report<S, K> {
+    /// Write this diagnostic as a single `file:line:col: kind[code]: message` line, optionally followed by the
+    /// location of each label.
+    pub(super) fn write_short<C: Cache<S::SourceId>, W: Write>(
+        &self,
+        cache: &mut C,
+        w: &mut WrappedWriter<W>,
+        s: StreamType,
+    ) -> io::Result<()> {
+        let kind_color = self.config.filter_color(self.kind.get_color(&self.config));
+        let code = self
+            .code
+            .as_ref()
+            .map(|c| format!("[{}]", self.config.escape_text(c)));
+        write!(
+            w,
+            "{}: {}",
+            self.short_location(cache, self.span.source(), self.span.start()),
+            format_args!(
+                "{}{}",
+                self.config
+                    .escape_text(&self.kind.to_string().to_lowercase()),
+                Show(code)
+            )
+            .fg(kind_color, s),
+        )?;
+        if let Some(msg) = &self.msg {
+            write!(w, ": {}", self.config.escape_text(msg))?;
+        }
+        writeln!(w)?;
+
+        if self.config.short_labels {
+            for label in &self.labels {
+                write!(
+                    w,
+                    "  {}",
+                    self.short_location(cache, label.span.source(), label.span.start())
+                )?;
+                if let Some(msg) = &label.display_info.msg {
+                    write!(w, ": {}", self.config.escape_text(msg))?;
+                }
+                writeln!(w)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The `file:line:col` of the given offset, or just the file name if the source cannot be fetched or does not
+    /// contain the offset.
+    fn short_location<C: Cache<S::SourceId>>(
+        &self,
+        cache: &mut C,
+        src_id: &S::SourceId,
+        offset: usize,
+    ) -> String {
+        let path = cache.absolute_path(src_id);
+        match fetch_source(cache, src_id, &self.config) {
+            Some((src, src_name)) => match char_location(src, offset, self.config.index_type) {
+                Some(location) => {
+                    let location = Loc(src, src_name, Some(location));
+                    Hyperlink(location.hyperlink(self.config.hyperlinks, path), location)
+                        .to_string()
+                }
+                None => src_name,
+            },
+            None => display_name(cache, src_id, &self.config),
+        }
+    }
+}
@@ -21,7 +21,7 @@ impl<Str: Display + Debug> Display for BasicStyle<Str> {
 
 impl<Str: Display + Debug> ReportStyle for BasicStyle<Str> {
     fn get_color(&self, config: &Config) -> Option<Color> {
-        Some(self.color).filter(|_| config.color)
+        config.filter_color(Some(self.color))
     }
 }
 /// A triat for coloring messages, requires Display for naming the Report error/warning/note etc
@@ -30,6 +30,11 @@ pub trait ReportStyle: Display + Debug {
     fn get_color(&self, _config: &Config) -> Option<Color> {
         None
     }
+
+    /// return the name to display for the Report, which may be translated using [`Config::with_locale`]
+    fn localised_name(&self, _config: &Config) -> String {
+        self.to_string()
+    }
 }
 
 impl ReportStyle for String {
@@ -10,7 +10,8 @@
 use insta::assert_snapshot;
 
 use crate::{
-    Cache, Config, FnCache, IndexType, Label, Report, ReportKind, ReportStyle, Source, Span,
+    sources, Cache, Config, Encoding, FnCache, Hyperlinks, IndexType, Label, Locale, OutputMode,
+    Report, ReportKind, ReportStyle, Source, Span,
 };
 
 impl<S: Span, K: ReportStyle> Report<S, K> {
@@ -247,6 +248,32 @@ fn byte_column() {
         ");
 }
 
+#[test]
+fn decoded_byte_spans() {
+    let source: Vec<u8> = "äpplë == örängë;".chars().map(|c| c as u8).collect();
+    let source = Source::decode(source, Encoding::Latin1).unwrap();
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 9..9)
+            .with_config(no_color().with_index_type(IndexType::Byte))
+            .with_message("can't compare äpplës with örängës")
+            .with_label(Label::new(0..5).with_message("This is an äpplë"))
+            .with_label(Label::new(9..15).with_message("This is an örängë"))
+            .finish()
+            .write_to_string(source),
+    );
+    assert_snapshot!(msg, @"
+        Error: can't compare äpplës with örängës
+           ╭─┤ <unknown>:1:10 │
+           │
+         1 │ äpplë == örängë;
+           │ ──┬──    ───┬──
+           │   ╰─────────│──── This is an äpplë
+           │             │
+           │             ╰──── This is an örängë
+        ───╯
+        ");
+}
+
 #[test]
 fn crossing_lines() {
     let source = "äpplë == örängë;";
@@ -973,3 +1000,680 @@ fn no_labels() {
         Note: code needs to exist
         ")
 }
+
+#[test]
+fn code() {
+    let source = "apple == orange;";
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(no_color())
+            .with_code("E0308")
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new(0..5).with_message("This is an apple"))
+            .finish()
+            .write_to_string(Source::from(source)),
+    );
+    assert_snapshot!(msg, @"
+        [E0308] Error: can't compare apples with oranges
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ apple == orange;
+           │ ──┬──
+           │   ╰──── This is an apple
+        ───╯
+        ")
+}
+
+#[test]
+fn short() {
+    let source = "apple ==\norange;";
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 6..8)
+            .with_config(no_color().with_output_mode(OutputMode::Short))
+            .with_code("E0308")
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new(0..5).with_message("This is an apple"))
+            .with_label(Label::new(9..15).with_message("This is an orange"))
+            .with_note("stop trying ... this is a fruitless endeavor")
+            .finish()
+            .write_to_string(Source::from(source)),
+    );
+    assert_snapshot!(msg, @"<unknown>:1:7: error[E0308]: can't compare apples with oranges")
+}
+
+#[test]
+fn short_out_of_range() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, ("a.rs", 40..42))
+            .with_config(
+                no_color()
+                    .with_output_mode(OutputMode::Short)
+                    .with_short_labels(true),
+            )
+            .with_message("m")
+            .with_label(Label::new(("a.rs", 50..51)).with_message("past the end"))
+            .finish()
+            .write_to_string(sources([("a.rs", "short")])),
+    );
+    assert_snapshot!(msg, @"
+        a.rs: error: m
+          a.rs: past the end
+        ");
+}
+
+#[test]
+fn short_with_labels() {
+    let source = "äpplë ==\norange;";
+    let msg = remove_trailing(
+        Report::build(ReportKind::Warning, 9..11)
+            .with_config(
+                no_color()
+                    .with_output_mode(OutputMode::Short)
+                    .with_short_labels(true)
+                    .with_index_type(IndexType::Byte),
+            )
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new(0..7).with_message("This is an apple"))
+            .with_label(Label::new(11..17))
+            .finish()
+            .write_to_string(Source::from(source)),
+    );
+    assert_snapshot!(msg, @"
+        <unknown>:1:8: warning: can't compare apples with oranges
+          <unknown>:1:1: This is an apple
+          <unknown>:2:1
+        ")
+}
+
+#[test]
+fn github() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, (0, 6..8))
+            .with_config(no_color().with_output_mode(OutputMode::GitHub))
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new((0, 0..5)).with_message("This is an apple"))
+            .with_label(Label::new((0, 9..15)).with_message("This is an orange, 100%"))
+            .with_label(Label::new((0, 3..12)))
+            .with_help("have you tried peeling the orange?")
+            .finish()
+            .write_to_string(multi_sources(&["apple ==\norange;"])),
+    );
+    assert_snapshot!(msg, @"
+        ::error file=0,line=1,endLine=1,col=1,endColumn=5,title=can't compare apples with oranges::This is an apple%0AHelp: have you tried peeling the orange?
+        ::error file=0,line=2,endLine=2,col=1,endColumn=6,title=can't compare apples with oranges::This is an orange, 100%25%0AHelp: have you tried peeling the orange?
+        ::error file=0,line=1,endLine=2,title=can't compare apples with oranges::can't compare apples with oranges%0AHelp: have you tried peeling the orange?
+        ")
+}
+
+#[test]
+fn gitlab() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Warning, (0, 6..8))
+            .with_config(no_color().with_output_mode(OutputMode::GitLab))
+            .with_code("W001")
+            .with_message("can't compare \"apples\" with oranges")
+            .with_label(Label::new((0, 0..5)).with_message("This is an apple"))
+            .with_label(Label::new((0, 9..15)))
+            .finish()
+            .write_to_string(multi_sources(&["apple ==\norange;"])),
+    );
+    assert_snapshot!(msg, @r#"
+        {"description":"can't compare \"apples\" with oranges: This is an apple","check_name":"W001","fingerprint":"983a38b84a324670","severity":"minor","location":{"path":"0","positions":{"begin":{"line":1,"column":1},"end":{"line":1,"column":5}}}}
+        {"description":"can't compare \"apples\" with oranges","check_name":"W001","fingerprint":"3f5b82c8e953375c","severity":"minor","location":{"path":"0","positions":{"begin":{"line":2,"column":1},"end":{"line":2,"column":6}}}}
+        "#)
+}
+
+#[test]
+fn quickfix() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, (0, 6..8))
+            .with_config(no_color().with_output_mode(OutputMode::Quickfix))
+            .with_code("E001")
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new((0, 0..5)).with_message("This is an apple"))
+            .with_label(Label::new((0, 9..15)))
+            .finish()
+            .write_to_string(multi_sources(&["apple ==\norange;"])),
+    );
+    assert_snapshot!(msg, @"
+        0:1:1: error[E001]: This is an apple (can't compare apples with oranges)
+        0:2:1: error[E001]: can't compare apples with oranges
+        ")
+}
+
+#[test]
+fn hyperlinks() {
+    struct LinkedCache(Source);
+
+    impl Cache<()> for LinkedCache {
+        type Storage = String;
+
+        fn fetch(&mut self, _: &()) -> Result<&Source, impl std::fmt::Debug> {
+            Ok::<_, ()>(&self.0)
+        }
+        fn display<'a>(&self, _: &'a ()) -> Option<impl std::fmt::Display + 'a> {
+            Some("fruit.txt")
+        }
+        fn absolute_path(&self, _: &()) -> Option<std::path::PathBuf> {
+            Some("/home/me/my fruit.txt".into())
+        }
+    }
+
+    let report = |hyperlinks| {
+        Report::build(ReportKind::Error, 9..15)
+            .with_config(no_color().with_compact(true).with_hyperlinks(hyperlinks))
+            .with_label(Label::new(9..15).with_message("This is an orange"))
+            .finish()
+            .write_to_string(LinkedCache(Source::from("apple ==\norange;".to_string())))
+    };
+    assert_snapshot!(
+        report(Hyperlinks::File).lines().nth(1).unwrap().escape_debug(),
+        @r#"   ╭─┤ \u{1b}]8;;file:///home/me/my%20fruit.txt#2\u{1b}\\fruit.txt:2:1\u{1b}]8;;\u{1b}\\ │"#
+    );
+    assert_snapshot!(
+        report(Hyperlinks::Template("vscode://file{path}:{line}:{col}")).lines().nth(1).unwrap().escape_debug(),
+        @r#"   ╭─┤ \u{1b}]8;;vscode://file/home/me/my%20fruit.txt:2:1\u{1b}\\fruit.txt:2:1\u{1b}]8;;\u{1b}\\ │"#
+    );
+}
+
+// `concolor` disables color when not writing to a terminal
+#[cfg(not(feature = "concolor"))]
+#[test]
+fn color_depth() {
+    use crate::{Color, ColorDepth};
+
+    let report = |depth| {
+        Report::build(ReportKind::Advice, 0..0)
+            .with_message("can't compare apples with oranges")
+            .with_label(
+                Label::new(0..5)
+                    .with_message("This is an apple")
+                    .with_color(Color::Rgb(250, 10, 10)),
+            )
+            .with_config(Config::default().with_color_depth(depth))
+            .finish()
+            .write_to_string(Source::from("apple == orange;"))
+    };
+
+    let ansi16 = report(ColorDepth::Ansi16);
+    assert!(!ansi16.contains("38;5;") && !ansi16.contains("38;2;"));
+    assert!(ansi16.contains("\x1b[91m"));
+
+    let ansi256 = report(ColorDepth::Ansi256);
+    assert!(ansi256.contains("\x1b[38;5;196m") && !ansi256.contains("38;2;"));
+
+    let true_color = report(ColorDepth::TrueColor);
+    assert!(true_color.contains("\x1b[38;2;250;10;10m") && !true_color.contains("38;5;"));
+}
+
+#[cfg(not(feature = "concolor"))]
+#[test]
+fn label_highlight() {
+    use crate::{Color, LabelHighlight};
+
+    let report = |highlight| {
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(Config::default().with_underlines(false))
+            .with_label(
+                Label::new(9..15)
+                    .with_color(Color::Green)
+                    .with_highlight(highlight),
+            )
+            .finish()
+            .write_to_string(Source::from("apple == orange;"))
+    };
+
+    assert!(report(LabelHighlight::Foreground).contains("\x1b[32mo\x1b[0m"));
+    assert!(report(LabelHighlight::Background).contains("\x1b[42mo\x1b[0m"));
+    assert!(report(LabelHighlight::Bold).contains("\x1b[1;32mo\x1b[0m"));
+    assert!(report(LabelHighlight::Underline).contains("\x1b[4;32mo\x1b[0m"));
+}
+
+#[cfg(not(feature = "concolor"))]
+#[test]
+fn label_highlight_without_color() {
+    use crate::LabelHighlight;
+
+    let report = |highlight, config: Config| {
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(config.with_underlines(false))
+            .with_label(Label::new(9..15).with_highlight(highlight))
+            .finish()
+            .write_to_string(Source::from("apple == orange;"))
+    };
+
+    // Attributes are drawn without a color, and backgrounds swap the terminal's colors instead.
+    let config = Config::default();
+    assert!(report(LabelHighlight::Foreground, config).contains("\x1b[0morange\x1b["));
+    assert!(report(LabelHighlight::Background, config).contains("\x1b[7mo\x1b[0m"));
+    assert!(report(LabelHighlight::Bold, config).contains("\x1b[1mo\x1b[0m"));
+    assert!(report(LabelHighlight::Underline, config).contains("\x1b[4mo\x1b[0m"));
+    assert!(report(LabelHighlight::Bold, no_color()).contains(" apple == orange;"));
+}
+
+#[test]
+fn show_invisibles() {
+    let source = "let\ta\u{a0}= b\u{200b}c;  \r\nd\u{7}\r";
+    let msg = Report::build(ReportKind::Error, 0..0)
+        .with_config(no_color().with_show_invisibles(true))
+        .with_message("unexpected characters")
+        .with_label(Label::new(8..11).with_message("This is one identifier"))
+        .with_label(Label::new(17..18).with_message("This is a bell"))
+        .finish()
+        .write_to_string(Source::from(source));
+    assert_snapshot!(msg, @"
+        Error: unexpected characters
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ let␉a·= b<U+200B>c;  
+           │         ─┬────────  
+           │          ╰────────── This is one identifier
+         2 │ d<U+0007>␍
+           │  ▲───────  
+           │  ╰───────── This is a bell
+        ───╯
+        ")
+}
+
+#[test]
+fn escaped_char_alignment() {
+    let msg = Report::build(ReportKind::Error, 0..0)
+        .with_config(no_color().with_show_invisibles(true))
+        .with_label(Label::new(0..2).with_message("ends with a zero-width space"))
+        .finish()
+        .write_to_string(Source::from("a\u{200b};"));
+    let lines: Vec<_> = msg.lines().collect();
+    let text = lines
+        .iter()
+        .find(|line| line.contains("a<U+200B>;"))
+        .unwrap();
+    let underline = lines[lines.iter().position(|line| line == text).unwrap() + 1];
+    // The underline should end under the `>` of the escaped character, not under the `;` after it
+    let col = |line: &str, i: usize| line[..i].chars().count();
+    assert_eq!(
+        underline.trim_end().chars().count() - 1,
+        col(text, text.find('>').unwrap())
+    );
+}
+
+#[test]
+fn escape_bidi() {
+    let source = "if access_level != \"user\u{202e} \u{2066}// Check if admin\u{2069} \u{2066}\" {";
+    let report = |config: Config| {
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(config.with_color(false))
+            .with_message("suspicious comparison with \"user\u{202e}\"")
+            .with_label(
+                Label::new(19..48)
+                    .with_message("This string contains \u{2066}bidi\u{2069} controls"),
+            )
+            .finish()
+            .write_to_string(Source::from(source))
+    };
+    assert_snapshot!(report(Config::default()), @r#"
+        Error: suspicious comparison with "user<U+202E>"
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ if access_level != "user<U+202E> <U+2066>// Check if admin<U+2069> <U+2066>" {
+           │                    ────────────────────────────┬────────────────────────────  
+           │                                                ╰────────────────────────────── This string contains <U+2066>bidi<U+2069> controls
+        ───╯
+        "#);
+    assert!(report(Config::default().with_escape_bidi(false)).contains('\u{202e}'));
+}
+
+#[test]
+fn escape_bidi_everywhere() {
+    let name = "admin\u{202e}sr.rs";
+    for mode in [
+        OutputMode::Full,
+        OutputMode::Short,
+        OutputMode::GitHub,
+        OutputMode::GitLab,
+        OutputMode::Quickfix,
+    ] {
+        let msg = Report::build(
+            ReportKind::Custom("Lint\u{2066}", crate::Color::Yellow),
+            (name, 0..0),
+        )
+        .with_config(no_color().with_output_mode(mode))
+        .with_code("L\u{2069}1")
+        .with_message("user\u{202e}")
+        .with_label(Label::new((name, 0..2)).with_message("\u{2066}bidi\u{2069}"))
+        .with_help("help\u{202e}")
+        .with_note("note\u{202e}")
+        .with_section("Caused by\u{202e}", "cause\u{202e}")
+        .finish()
+        .write_to_string(sources([(name, "ok")]));
+        assert!(
+            !msg.contains(['\u{202e}', '\u{2066}', '\u{2069}']),
+            "{}",
+            msg
+        );
+        assert!(msg.contains("admin<U+202E>sr.rs"), "{}", msg);
+    }
+}
+
+#[test]
+fn locale() {
+    struct Anonymous(Source<&'static str>);
+
+    impl Cache<()> for Anonymous {
+        type Storage = &'static str;
+
+        fn fetch(&mut self, _: &()) -> Result<&Source<&'static str>, impl std::fmt::Debug> {
+            Ok::<_, ()>(&self.0)
+        }
+        fn display<'a>(&self, _: &'a ()) -> Option<impl std::fmt::Display + 'a> {
+            None::<&str>
+        }
+    }
+
+    let msg = remove_trailing(
+        Report::build(ReportKind::Warning, 0..0)
+            .with_config(
+                no_color().with_locale(
+                    Locale::new()
+                        .with_warning("警告")
+                        .with_help("ヘルプ")
+                        .with_note("注")
+                        .with_unknown_source("<不明>")
+                        .with_numbered("{name}{n}"),
+                ),
+            )
+            .with_message("りんごとみかんは比較できません")
+            .with_label(Label::new(0..5).with_message("これはりんごです"))
+            .with_help("みかんの皮を\nむいてみましたか？")
+            .with_note("一つ目")
+            .with_note("二つ目")
+            .finish()
+            .write_to_string(Anonymous(Source::from("apple == orange;"))),
+    );
+    assert_snapshot!(msg, @"
+        警告: りんごとみかんは比較できません
+           ╭─┤ <不明>:1:1 │
+           │
+         1 │ apple == orange;
+           │ ──┬──
+           │   ╰──── これはりんごです
+           │
+           │ ヘルプ: みかんの皮を
+           │         むいてみましたか？
+           │
+           │ 注1: 一つ目
+           │ 注2: 二つ目
+        ───╯
+        ")
+}
+
+#[test]
+fn sections() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(no_color())
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new(0..15).with_message("This comparison is invalid"))
+            .with_section("Expected", "an apple")
+            .with_section("Found", "an orange")
+            .with_note("fruit is not comparable")
+            .with_section("Expected", "a comparable value,\nsuch as a number")
+            .with_section_color("See also", crate::Color::Blue)
+            .with_section("See also", "https://example.com/fruit")
+            .with_help("try comparing two apples")
+            .finish()
+            .write_to_string(Source::from("apple == orange;")),
+    );
+    assert_snapshot!(msg, @"
+        Error: can't compare apples with oranges
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ apple == orange;
+           │ ───────┬───────
+           │        ╰───────── This comparison is invalid
+           │
+           │ Expected 1: an apple
+           │ Expected 2: a comparable value,
+           │             such as a number
+           │
+           │ Found: an orange
+           │
+           │ Help: try comparing two apples
+           │
+           │ Note: fruit is not comparable
+           │
+           │ See also: https://example.com/fruit
+        ───╯
+        ")
+}
+
+#[test]
+fn label_notes() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(no_color())
+            .with_message("can't compare apples with oranges")
+            .with_label(
+                Label::new(0..5)
+                    .with_message("This is an apple")
+                    .with_note("apples are red\nor green"),
+            )
+            .with_label(
+                Label::new(9..15)
+                    .with_message("This is an orange")
+                    .with_note("oranges are orange"),
+            )
+            .finish()
+            .write_to_string(Source::from("apple == orange;")),
+    );
+    assert_snapshot!(msg, @"
+        Error: can't compare apples with oranges
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ apple == orange;
+           │ ──┬──    ───┬──
+           │   ╰─────────│──── This is an apple
+           │             │     Note: apples are red
+           │             │           or green
+           │             │
+           │             ╰──── This is an orange
+           │                   Note: oranges are orange
+        ───╯
+        ")
+}
+
+#[test]
+fn label_notes_without_message() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, ("a.rs", 0..0))
+            .with_config(no_color())
+            .with_message("can't compare apples with oranges")
+            .with_label(Label::new(("a.rs", 0..5)).with_note("apples are red\nor green"))
+            .with_label(
+                Label::new(("a.rs", 9..15))
+                    .with_message("This is an orange")
+                    .with_note("oranges are orange"),
+            )
+            .with_label(Label::new(("b.rs", 0..4)).with_note("pears are not compared"))
+            .with_note("fruit is not comparable")
+            .finish()
+            .write_to_string(sources([("a.rs", "apple == orange;"), ("b.rs", "pear")])),
+    );
+    assert_snapshot!(msg, @"
+        Error: can't compare apples with oranges
+           ╭─┤ a.rs:1:1 │
+           │
+         1 │ apple == orange;
+           │ ─────    ───┬──
+           │             │
+           │             ╰──── This is an orange
+           │                   Note: oranges are orange
+           │
+           │ Note: apples are red
+           │       or green
+           │
+           ├─┤ b.rs:1:1 │
+           │
+         1 │ pear
+           │ ────
+           │
+           │ Note: pears are not compared
+           │
+           │ Note: fruit is not comparable
+        ───╯
+        ");
+}
+
+#[test]
+fn multiline_label_notes() {
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, 0..0)
+            .with_config(no_color())
+            .with_message("can't compare apples with oranges")
+            .with_label(
+                Label::new(0..20)
+                    .with_message("This is a comparison")
+                    .with_note("comparisons need two values of the same type"),
+            )
+            .with_label(
+                Label::new(9..15)
+                    .with_message("This is an orange")
+                    .with_note("oranges are orange"),
+            )
+            .with_label(Label::new(19..24).with_message("This is an apple"))
+            .finish()
+            .write_to_string(Source::from("apple ==\norange\n== apple;")),
+    );
+    assert_snapshot!(msg, @"
+        Error: can't compare apples with oranges
+           ╭─┤ <unknown>:1:1 │
+           │
+         1 │ ╭─▶ apple ==
+         2 │ │   orange
+           │ │   ───┬──
+           │ │      ╰──── This is an orange
+           │ │            Note: oranges are orange
+         3 │ ├─▶ == apple;
+           │ │      ──┬──
+           │ ╰────────│───── This is a comparison
+           │          │      Note: comparisons need two values of the same type
+           │          │
+           │          ╰───── This is an apple
+        ───╯
+        ")
+}
+
+#[test]
+fn concurrent_rendering() {
+    use crate::{FnCache, SharedCache};
+
+    let cache = || {
+        SharedCache::new(FnCache::new(|id: &String| {
+            Ok::<_, ()>(format!("fn {}() {{\n    let x = {}_value;\n}}\n", id, id))
+        }))
+    };
+    let render = |cache: &mut SharedCache<_, _>, i: usize| {
+        let id = format!("module_{}", i % 8);
+        let x = id.len() + 16..id.len() + 17;
+        remove_trailing(
+            Report::build(ReportKind::Error, (id.clone(), x.clone()))
+                .with_config(no_color())
+                .with_message(format!("error in report {}", i))
+                .with_label(Label::new((id, x)).with_message("unused variable"))
+                .finish()
+                .write_to_string(cache),
+        )
+    };
+
+    // Every worker renders reports for every module, so they all race to fetch the same sources.
+    let shared = cache();
+    let rendered: Vec<Vec<String>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let mut cache = shared.clone();
+                scope.spawn(move || {
+                    (0..64)
+                        .map(|i| render(&mut cache, worker * 64 + i))
+                        .collect()
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let mut sequential = cache();
+    for (worker, outputs) in rendered.iter().enumerate() {
+        for (i, output) in outputs.iter().enumerate() {
+            assert_eq!(output, &render(&mut sequential, worker * 64 + i));
+        }
+    }
+    assert_snapshot!(rendered[0][3], @"
+        Error: error in report 3
+           ╭─┤ module_3:2:9 │
+           │
+         2 │     let x = module_3_value;
+           │         ▲
+           │         ╰── unused variable
+        ───╯
+        ");
+}
+
+#[test]
+fn bom_label_column() {
+    let source = Source::from("\u{FEFF}let a = 1;\nlet b = a;\n");
+    // The byte order mark is not counted in columns, so the displayed `1:5` is line 0, column 4.
+    let start = source.line_col_to_offset(0, 4).unwrap();
+    assert_eq!(start, 5);
+    assert_eq!(source.get_offset_line(start).unwrap().col_idx, 4);
+    let byte_offset = source.line_col_to_byte(0, 4).unwrap();
+    assert_eq!(byte_offset, '\u{FEFF}'.len_utf8() + 4);
+    assert_eq!(source.get_byte_line(byte_offset).unwrap().col_idx, 4);
+    let msg = remove_trailing(
+        Report::build(ReportKind::Error, start..start + 1)
+            .with_config(no_color())
+            .with_message("Unused variable")
+            .with_label(Label::new(start..start + 1).with_message("this is never read"))
+            .finish()
+            .write_to_string(&source),
+    );
+    assert_snapshot!(msg, @"
+        Error: Unused variable
+           ╭─┤ <unknown>:1:5 │
+           │
+         1 │ let a = 1;
+           │     ▲
+           │     ╰── this is never read
+        ───╯
+        ");
+}
+
+#[test]
+fn bom_and_line_endings() {
+    let source = Source::from("\u{FEFF}let a = 1;\r\nlet b = 2;\nlet c = 3;\r\n");
+    let mut report = Report::build(ReportKind::Warning, 5..6)
+        .with_config(no_color())
+        .with_message("Mixed line endings");
+    for line in source.lines() {
+        if let Some(ending) = source.line_ending(line) {
+            let end = line.span().end;
+            report.add_label(
+                Label::new(end - ending.char_len()..end).with_message(format!("{:?}", ending)),
+            );
+        }
+    }
+    let msg = remove_trailing(report.finish().write_to_string(&source));
+    assert_snapshot!(msg, @"
+        Warning: Mixed line endings
+           ╭─┤ <unknown>:1:5 │
+           │
+         1 │ let a = 1;
+           │           ─┐
+           │            ╰── CrLf
+         2 │ let b = 2;
+           │           ▲
+           │           ╰── Lf
+         3 │ let c = 3;
+           │           ─┐
+           │            ╰── CrLf
+        ───╯
+        ");
+}
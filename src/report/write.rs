@@ -1,12 +1,14 @@
-use std::borrow::Cow;
 use std::fmt::Display;
 use std::io;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use unicode_width::UnicodeWidthStr;
 
 use crate::source::Location;
-use crate::{Config, IndexType, LabelDisplay, Source};
+use crate::{Config, Hyperlinks, IndexType, LabelDisplay, LabelHighlight, OutputMode, Source};
 
-use super::draw::{self, StreamAwareFmt, StreamType, WrappedWriter};
+use super::draw::{self, Highlighted, StreamAwareFmt, StreamType, WrappedWriter};
 use super::{Cache, CharSet, LabelAttach, Report, ReportStyle, Rept, Show, Span, Write};
 
 // A WARNING, FOR ALL YE WHO VENTURE IN HERE
@@ -33,10 +35,7 @@ struct LabelInfo<'a> {
 
 impl LabelInfo<'_> {
     fn last_offset(&self) -> usize {
-        self.char_span
-            .end
-            .saturating_sub(1)
-            .max(self.char_span.start)
+        last_offset(&self.char_span)
     }
 
     fn display_range(&self, config: &Config) -> Range<usize> {
@@ -58,65 +57,24 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
         for label in self.labels.iter() {
             let label_source = label.span.source();
 
-            let Some((src, _src_name)) = fetch_source(cache, label_source) else {
+            let Some((src, _src_name)) = fetch_source(cache, label_source, &self.config) else {
                 continue;
             };
 
-            let given_label_span = label.span.start()..label.span.end();
-
-            let (label_char_span, start_line, end_line) = match self.config.index_type {
-                IndexType::Char => {
-                    let Some(start_line) = src.get_offset_line(given_label_span.start) else {
-                        continue;
-                    };
-                    let end_line = if given_label_span.start >= given_label_span.end {
-                        start_line.line_idx
-                    } else {
-                        let Some(end_line) = src.get_offset_line(given_label_span.end - 1) else {
-                            continue;
-                        };
-                        end_line.line_idx
-                    };
-                    (given_label_span, start_line.line_idx, end_line)
-                }
-                IndexType::Byte => {
-                    let Some(start_location) = src.get_byte_line(given_label_span.start) else {
-                        continue;
-                    };
-                    let line_text = src.get_line_text(start_location.line).unwrap();
-
-                    let num_chars_before_start = line_text
-                        [..start_location.col_idx.min(line_text.len())]
-                        .chars()
-                        .count();
-                    let start_char_offset = start_location.line.offset() + num_chars_before_start;
-
-                    if given_label_span.start >= given_label_span.end {
-                        (
-                            start_char_offset..start_char_offset,
-                            start_location.line_idx,
-                            start_location.line_idx,
-                        )
-                    } else {
-                        // We can subtract 1 from end, because get_byte_line doesn't actually index into the text.
-                        let end_pos = given_label_span.end - 1;
-                        let Some(end_location) = src.get_byte_line(end_pos) else {
-                            continue;
-                        };
-                        let end_line_text = src.get_line_text(end_location.line).unwrap();
-                        // Have to add 1 back now, so we don't cut a char in two.
-                        let num_chars_before_end =
-                            end_line_text[..end_location.col_idx + 1].chars().count();
-                        let end_char_offset = end_location.line.offset() + num_chars_before_end;
-
-                        (
-                            start_char_offset..end_char_offset,
-                            start_location.line_idx,
-                            end_location.line_idx,
-                        )
-                    }
-                }
+            let Some(label_char_span) = char_span(
+                src,
+                label.span.start()..label.span.end(),
+                self.config.index_type,
+            ) else {
+                continue;
+            };
+            let Some(start_line) = src.get_offset_line(label_char_span.start) else {
+                continue;
             };
+            let Some(end_line) = src.get_offset_line(last_offset(&label_char_span)) else {
+                continue;
+            };
+            let (start_line, end_line) = (start_line.line_idx, end_line.line_idx);
 
             let label_info = LabelInfo {
                 kind: if start_line == end_line {
@@ -191,7 +149,14 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
         w: W,
         s: StreamType,
     ) -> io::Result<()> {
-        let mut w = WrappedWriter::new(w, &self.config);
+        let mut w = WrappedWriter::new(w, &self.config, s);
+        match self.config.output_mode {
+            OutputMode::Full => {}
+            OutputMode::Short => return self.write_short(&mut cache, &mut w, s),
+            OutputMode::GitHub => return self.write_github(&mut cache, &mut w),
+            OutputMode::GitLab => return self.write_gitlab(&mut cache, &mut w),
+            OutputMode::Quickfix => return self.write_quickfix(&mut cache, &mut w),
+        }
         let draw = match self.config.char_set {
             CharSet::Unicode => draw::Characters::unicode(),
             CharSet::Ascii => draw::Characters::ascii(),
@@ -199,12 +164,23 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
 
         // --- Header ---
 
-        let kind_color = self.kind.get_color(&self.config);
+        // Custom styles may not have converted their color to the configured depth
+        let kind_color = self.config.filter_color(self.kind.get_color(&self.config));
+        let code = self
+            .code
+            .as_ref()
+            .map(|c| format!("[{}] ", self.config.escape_text(c)));
         writeln!(
             w,
             "{}: {}",
-            (&self.kind).fg(kind_color, s),
-            Show(self.msg.as_ref())
+            format_args!(
+                "{}{}",
+                Show(code),
+                self.config
+                    .escape_text(&self.kind.localised_name(&self.config))
+            )
+            .fg(kind_color, s),
+            Show(self.msg.as_deref().map(|msg| self.config.escape_text(msg)))
         )?;
 
         let groups = self.get_source_groups(&mut cache);
@@ -257,7 +233,8 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
             },
         ) in groups.iter().enumerate()
         {
-            let Some((src, src_name)) = fetch_source(&mut cache, src_id) else {
+            let path = cache.absolute_path(src_id);
+            let Some((src, src_name)) = fetch_source(&mut cache, src_id, &self.config) else {
                 // `fetch_source` should have reported the error.
                 continue;
             };
@@ -269,26 +246,8 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
                 // This has already been converted from bytes to chars, if applicable.
                 (labels[0].char_span.start, IndexType::Char)
             };
-            let location = Loc(
-                src,
-                src_name,
-                match index_type {
-                    IndexType::Char => src.get_offset_line(location),
-                    IndexType::Byte => src.get_byte_line(location).map(|location| {
-                        let line_text = src.get_line_text(location.line).unwrap();
-
-                        let col = line_text[..location.col_idx.min(line_text.len())]
-                            .chars()
-                            .count();
-
-                        Location {
-                            line: location.line,
-                            line_idx: location.line_idx,
-                            col_idx: col,
-                        }
-                    }),
-                },
-            );
+            let location = Loc(src, src_name, char_location(src, location, index_type));
+            let location = Hyperlink(location.hyperlink(self.config.hyperlinks, path), location);
             let corner_char = if group_idx == 0 {
                 draw.ltop
             } else {
@@ -703,26 +662,30 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
 
                 // Line
                 if !is_ellipsis {
-                    for (col, c) in src
-                        .get_line_text(line)
-                        .unwrap()
-                        .trim_end()
+                    for (col, c) in self
+                        .config
+                        .trim_line(src.get_line_text(line).unwrap())
                         .chars()
                         .enumerate()
                     {
-                        let color = if let Some(highlight) = get_highlight(col) {
-                            highlight.display_info.color
-                        } else {
-                            self.config.unimportant_color()
-                        };
-                        let (c, width) = self.config.char_width(c, col);
-                        if c.is_whitespace() {
-                            for _ in 0..width {
-                                write!(w, "{}", c.fg(color, s))?;
-                            }
+                        // A byte order mark has no width, so leave it out rather than writing it mid-line.
+                        if idx == 0 && col == 0 && src.has_bom() && !self.config.show_invisibles {
+                            continue;
+                        }
+                        let (color, highlight) = if let Some(highlight) = get_highlight(col) {
+                            (
+                                highlight.display_info.color,
+                                highlight.display_info.highlight,
+                            )
                         } else {
-                            write!(w, "{}", c.fg(color, s))?;
+                            (self.config.unimportant_color(), LabelHighlight::Foreground)
                         };
+                        let c = self.config.char_display(c, col);
+                        write!(
+                            w,
+                            "{}",
+                            Highlighted::new(c, color, highlight, self.config.color, s)
+                        )?;
                     }
                 }
                 writeln!(w)?;
@@ -744,10 +707,14 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
                             &margin_label,
                         )?;
                         // Lines alternate
-                        let mut chars = src.get_line_text(line).unwrap().trim_end().chars();
+                        let mut chars = self
+                            .config
+                            .trim_line(src.get_line_text(line).unwrap())
+                            .chars();
                         for col in 0..arrow_len {
-                            let width =
-                                chars.next().map_or(1, |c| self.config.char_width(c, col).1);
+                            let width = chars
+                                .next()
+                                .map_or(1, |c| self.config.char_display(c, col).width());
 
                             let vbar = get_vbar(col, row);
                             let underline = get_underline(col).filter(|_| row == 0);
@@ -803,9 +770,14 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
                         &margin_label,
                     )?;
                     // Lines
-                    let mut chars = src.get_line_text(line).unwrap().trim_end().chars();
+                    let mut chars = self
+                        .config
+                        .trim_line(src.get_line_text(line).unwrap())
+                        .chars();
                     for col in 0..arrow_len {
-                        let width = chars.next().map_or(1, |c| self.config.char_width(c, col).1);
+                        let width = chars
+                            .next()
+                            .map_or(1, |c| self.config.char_display(c, col).width());
 
                         let is_hbar = (((col > line_label.col) ^ line_label.multi.is_some())
                             || (line_label.label.display_info.msg.is_some()
@@ -857,62 +829,136 @@ impl<S: Span, K: ReportStyle> Report<S, K> {
                         }
                     }
                     if line_label.draw_msg {
-                        write!(w, " {}", Show(line_label.label.display_info.msg.as_ref()))?;
+                        write!(
+                            w,
+                            " {}",
+                            Show(
+                                line_label
+                                    .label
+                                    .display_info
+                                    .msg
+                                    .as_deref()
+                                    .map(|msg| self.config.escape_text(msg))
+                            )
+                        )?;
                     }
                     writeln!(w)?;
+
+                    // Label notes, beneath the message and between the arrows of the labels still to come
+                    if !line_label.draw_msg {
+                        continue;
+                    }
+                    for note in &line_label.label.display_info.notes {
+                        let note = self.config.escape_text(note);
+                        for (i, note_line) in note.split('\n').enumerate() {
+                            write_margin_and_arrows(
+                                &mut w,
+                                idx,
+                                false,
+                                is_ellipsis,
+                                Some((row + 1, false)),
+                                &line_labels,
+                                &margin_label,
+                            )?;
+                            let mut chars = self
+                                .config
+                                .trim_line(src.get_line_text(line).unwrap())
+                                .chars();
+                            for col in 0..arrow_len {
+                                let width = chars
+                                    .next()
+                                    .map_or(1, |c| self.config.char_display(c, col).width());
+                                let c = match get_vbar(col, row + 1) {
+                                    Some(vbar_ll) => {
+                                        draw.vbar.fg(vbar_ll.label.display_info.color, s)
+                                    }
+                                    None => ' '.fg(None, s),
+                                };
+                                if width > 0 {
+                                    write!(w, "{c}")?;
+                                }
+                                for _ in 1..width {
+                                    write!(w, " ")?;
+                                }
+                            }
+                            if i == 0 {
+                                writeln!(
+                                    w,
+                                    " {}: {note_line}",
+                                    self.config.locale.note.fg(self.config.note_color(), s)
+                                )?;
+                            } else {
+                                writeln!(
+                                    w,
+                                    " {:>pad$}{note_line}",
+                                    "",
+                                    pad = self.config.locale.note.width() + 2
+                                )?;
+                            }
+                        }
+                    }
                 }
             }
-        }
 
-        // Help
-        for (i, help) in self.help.iter().enumerate() {
-            if !self.config.compact && i == 0 {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(w)?;
-            }
-            let help_prefix = if self.help.len() > 1 && self.config.enumerate_helps {
-                format!("{} {}", "Help", i + 1).into()
-            } else {
-                Cow::Borrowed("Help")
-            };
-            let mut lines = help.split('\n');
-            if let Some(line) = lines.next() {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(
-                    w,
-                    "{}: {line}",
-                    help_prefix.as_ref().fg(self.config.note_color(), s),
-                )?;
-            }
-            for line in lines {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(w, "{:>pad$}{line}", "", pad = help_prefix.len() + 2)?;
+            // Labels without a message have no arrow to draw their notes beneath, so list them after the group
+            let notes = labels
+                .iter()
+                .filter(|label_info| label_info.display_info.msg.is_none())
+                .flat_map(|label_info| &label_info.display_info.notes);
+            for (i, note) in notes.enumerate() {
+                if !self.config.compact && i == 0 {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(w)?;
+                }
+                let note = self.config.escape_text(note);
+                let mut lines = note.split('\n');
+                if let Some(line) = lines.next() {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(
+                        w,
+                        "{}: {line}",
+                        self.config.locale.note.fg(self.config.note_color(), s)
+                    )?;
+                }
+                for line in lines {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(
+                        w,
+                        "{:>pad$}{line}",
+                        "",
+                        pad = self.config.locale.note.width() + 2
+                    )?;
+                }
             }
         }
 
-        // Notes
-        for (i, note) in self.notes.iter().enumerate() {
-            if !self.config.compact && i == 0 {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(w)?;
-            }
-            let note_prefix = if self.notes.len() > 1 && self.config.enumerate_notes {
-                format!("{} {}", "Note", i + 1).into()
-            } else {
-                Cow::Borrowed("Note")
-            };
-            let mut lines = note.split('\n');
-            if let Some(line) = lines.next() {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(
-                    w,
-                    "{}: {line}",
-                    note_prefix.as_ref().fg(self.config.note_color(), s),
-                )?;
-            }
-            for line in lines {
-                write_margin(&mut w, 0, false, false)?;
-                writeln!(w, "{:>pad$}{line}", "", pad = note_prefix.len() + 2)?;
+        // Footer sections: helps, notes and custom sections, in the order they were added
+        for section in &self.sections {
+            let name = self.config.escape_text(section.name(&self.config));
+            let color = self
+                .config
+                .filter_color(section.color)
+                .or_else(|| self.config.note_color());
+            for (i, item) in section.items.iter().enumerate() {
+                if !self.config.compact && i == 0 {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(w)?;
+                }
+                let prefix = if section.items.len() > 1 && section.enumerate(&self.config) {
+                    self.config.locale.enumerate(&name, i + 1).into()
+                } else {
+                    name.clone()
+                };
+                let item = self.config.escape_text(item);
+                let mut lines = item.split('\n');
+                if let Some(line) = lines.next() {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(w, "{}: {line}", prefix.as_ref().fg(color, s))?;
+                }
+                for line in lines {
+                    write_margin(&mut w, 0, false, false)?;
+                    writeln!(w, "{:>pad$}{line}", "", pad = prefix.width() + 2)?;
+                }
             }
         }
 
@@ -947,11 +993,12 @@ impl LineLabel<'_> {
     }
 }
 
-fn fetch_source<'a, Id: ?Sized, C: Cache<Id>>(
+pub(super) fn fetch_source<'a, Id: ?Sized, C: Cache<Id>>(
     cache: &'a mut C,
     src_id: &Id,
+    config: &Config,
 ) -> Option<(&'a Source<C::Storage>, String)> {
-    let src_name = display_name(cache, src_id);
+    let src_name = display_name(cache, src_id, config);
     match cache.fetch(src_id) {
         Ok(src) => Some((src, src_name)),
         Err(err) => {
@@ -961,11 +1008,15 @@ fn fetch_source<'a, Id: ?Sized, C: Cache<Id>>(
     }
 }
 
-fn display_name<Id: ?Sized, C: Cache<Id>>(cache: &C, src_id: &Id) -> String {
+pub(super) fn display_name<Id: ?Sized, C: Cache<Id>>(
+    cache: &C,
+    src_id: &Id,
+    config: &Config,
+) -> String {
     cache
         .display(src_id)
-        .map(|d| d.to_string())
-        .unwrap_or_else(|| "<unknown>".to_string())
+        .map(|d| config.escape_text(&d.to_string()).into_owned())
+        .unwrap_or_else(|| config.locale.unknown_source.to_string())
 }
 
 fn max_line_num<S: Span>(groups: &[SourceGroup<'_, S>]) -> Option<usize> {
@@ -980,20 +1031,161 @@ fn nb_digits(value: usize) -> usize {
     value.checked_ilog10().unwrap_or(0) as usize + 1
 }
 
+/// Find the line and (character) column of the given offset, which is interpreted according to `index_type`. The
+/// column counts a byte order mark; see [`Source::display_col`].
+pub(super) fn char_location<I: AsRef<str>>(
+    src: &Source<I>,
+    offset: usize,
+    index_type: IndexType,
+) -> Option<Location> {
+    match index_type {
+        IndexType::Char => src.locate(offset),
+        IndexType::Byte => src.locate_byte(offset).map(|location| Location {
+            col_idx: src.char_col(location.line, location.col_idx),
+            ..location
+        }),
+    }
+}
+
+/// Convert a span, interpreted according to `index_type`, into a span of character offsets.
+pub(super) fn char_span<I: AsRef<str>>(
+    src: &Source<I>,
+    span: Range<usize>,
+    index_type: IndexType,
+) -> Option<Range<usize>> {
+    match index_type {
+        IndexType::Char => src.get_offset_line(span.start).map(|_| span),
+        IndexType::Byte => {
+            let start_location = src.locate_byte(span.start)?;
+            let start_char_offset = start_location.line.offset()
+                + src.char_col(start_location.line, start_location.col_idx);
+
+            if span.start >= span.end {
+                Some(start_char_offset..start_char_offset)
+            } else {
+                // We can subtract 1 from end, because locate_byte doesn't actually index into the text.
+                let end_location = src.locate_byte(span.end - 1)?;
+                // Count the char that the end is in too, which a decoded source may map to the start of.
+                let end_char_offset = end_location.line.offset()
+                    + src.char_col(end_location.line, end_location.col_idx + 1);
+
+                Some(start_char_offset..end_char_offset)
+            }
+        }
+    }
+}
+
+/// The offset of the last character in a span, or its start if it is empty.
+fn last_offset(span: &Range<usize>) -> usize {
+    span.end.saturating_sub(1).max(span.start)
+}
+
+/// The one-indexed line and column of the first and last characters of a span within a source.
+pub(super) struct SpanPosition {
+    pub(super) src_name: String,
+    pub(super) start: (usize, usize),
+    pub(super) end: (usize, usize),
+}
+
+/// Find the position of a span, for output formats that describe where labels are rather than drawing them.
+pub(super) fn span_position<Id: ?Sized, C: Cache<Id>>(
+    cache: &mut C,
+    src_id: &Id,
+    span: Range<usize>,
+    config: &Config,
+) -> Option<SpanPosition> {
+    let (src, src_name) = fetch_source(cache, src_id, config)?;
+    let span = char_span(src, span, config.index_type)?;
+    let position = |offset| {
+        src.get_offset_line(offset).map(|location| {
+            (
+                location.line_idx + 1 + src.display_line_offset(),
+                location.col_idx + 1,
+            )
+        })
+    };
+    Some(SpanPosition {
+        start: position(span.start)?,
+        end: position(last_offset(&span))?,
+        src_name,
+    })
+}
+
 #[derive(Debug, Clone)]
-struct Loc<'src, I: AsRef<str>>(&'src Source<I>, String, Option<Location>);
+pub(super) struct Loc<'src, I: AsRef<str>>(
+    pub(super) &'src Source<I>,
+    pub(super) String,
+    pub(super) Option<Location>,
+);
+
+impl<I: AsRef<str>> Loc<'_, I> {
+    /// The one-indexed line and column of this location, as displayed.
+    fn line_col(&self) -> Option<(usize, usize)> {
+        self.2.as_ref().map(|location| {
+            (
+                location.line_idx + 1 + self.0.display_line_offset(),
+                self.0.display_col(location) + 1,
+            )
+        })
+    }
+
+    /// The URL that this location should link to, according to the given hyperlink style.
+    pub(super) fn hyperlink(
+        &self,
+        hyperlinks: Hyperlinks,
+        path: Option<PathBuf>,
+    ) -> Option<String> {
+        let template = match hyperlinks {
+            Hyperlinks::Off => return None,
+            Hyperlinks::File => "file://{path}#{line}",
+            Hyperlinks::Template(template) => template,
+        };
+        let (line, col) = self.line_col()?;
+        Some(
+            template
+                .replace("{path}", &url_path(&path?))
+                .replace("{line}", &line.to_string())
+                .replace("{col}", &col.to_string()),
+        )
+    }
+}
 
 impl<I: AsRef<str>> Display for Loc<'_, I> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.2.as_ref() {
-            Some(location) => write!(
-                f,
-                "{}:{}:{}",
-                self.1,
-                location.line_idx + 1 + self.0.display_line_offset(),
-                location.col_idx + 1,
-            ),
+        match self.line_col() {
+            Some((line, col)) => write!(f, "{}:{line}:{col}", self.1),
             None => write!(f, ":?:?"),
         }
     }
 }
+
+/// Text that links to a URL, if it has one, using an OSC 8 escape sequence.
+pub(super) struct Hyperlink<T>(pub(super) Option<String>, pub(super) T);
+
+impl<T: Display> Display for Hyperlink<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(url) => write!(f, "\x1b]8;;{url}\x1b\\{}\x1b]8;;\x1b\\", self.1),
+            None => self.1.fmt(f),
+        }
+    }
+}
+
+/// Write an absolute path as it would appear in a `file://` URL.
+fn url_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::with_capacity(path.len() + 1);
+    // Windows paths start with a drive letter rather than a separator
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    url
+}
@@ -0,0 +1,243 @@
+//! Conversion of rustc's JSON diagnostics into [`Report`]s.
+//!
+//! `rustc --error-format=json` and `cargo build --message-format=json` describe each diagnostic as a line of JSON.
+//! [`parse_message`] reads one such line and [`Diagnostic::to_report`] turns the result into a [`Report`], so that
+//! rustc's errors can be rendered in the same style as those of any other tool using this crate.
+//!
+//! ```no_run
+//! use ariadne::{rustc, Config, FileCache};
+//! use std::io::BufRead;
+//!
+//! let mut cache = FileCache::default();
+//! for line in std::io::stdin().lock().lines() {
+//!     if let Ok(Some(diagnostic)) = rustc::parse_message(&line.unwrap()) {
+//!         diagnostic.to_report(Config::default()).eprint(&mut cache).unwrap();
+//!     }
+//! }
+//! ```
+
+use std::{ops::Range, path::Path};
+
+use serde::Deserialize;
+
+use crate::{Color, Config, IndexType, Label, Report, ReportKind, ReportStyle};
+
+/// The span type of reports produced from rustc diagnostics: a file path and a byte range within it.
+pub type RustcSpan<'a> = (&'a Path, Range<usize>);
+
+/// A diagnostic emitted by rustc, as found in its JSON output.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Diagnostic {
+    /// The primary message.
+    pub message: String,
+    /// The diagnostic code, if any.
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    /// The severity: `"error"`, `"warning"`, `"note"`, `"help"`, `"failure-note"` or
+    /// `"error: internal compiler error"`.
+    pub level: String,
+    /// The locations this diagnostic refers to.
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    /// Attached notes, helps and suggestions.
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    /// rustc's own rendering of the diagnostic.
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+/// The code of a [`Diagnostic`], such as `E0308`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiagnosticCode {
+    /// The code itself.
+    pub code: String,
+    /// A longer explanation of the code, if rustc has one.
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+/// A location referred to by a [`Diagnostic`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiagnosticSpan {
+    /// The path of the file, relative to the directory rustc was run in.
+    pub file_name: String,
+    /// The byte offset of the start of the span.
+    pub byte_start: usize,
+    /// The (exclusive) byte offset of the end of the span.
+    pub byte_end: usize,
+    /// The one-indexed line of the start of the span.
+    pub line_start: usize,
+    /// The one-indexed line of the end of the span.
+    pub line_end: usize,
+    /// The one-indexed character column of the start of the span.
+    pub column_start: usize,
+    /// The one-indexed (exclusive) character column of the end of the span.
+    pub column_end: usize,
+    /// Whether this is the main location of the diagnostic.
+    pub is_primary: bool,
+    /// The message attached to this location.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The text suggested as a replacement for this span.
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Message {
+    Cargo {
+        reason: String,
+        #[serde(default)]
+        message: Option<Diagnostic>,
+    },
+    Rustc(Diagnostic),
+    Other(serde::de::IgnoredAny),
+}
+
+/// Parse a single line of rustc's `--error-format=json` output or cargo's `--message-format=json` output.
+///
+/// Returns `Ok(None)` for valid JSON that does not describe a diagnostic (such as cargo's `compiler-artifact`
+/// messages), and an error if the line is not JSON at all.
+pub fn parse_message(line: &str) -> Result<Option<Diagnostic>, serde_json::Error> {
+    Ok(match serde_json::from_str(line)? {
+        Message::Cargo { reason, message } if reason == "compiler-message" => message,
+        Message::Cargo { .. } | Message::Other(_) => None,
+        Message::Rustc(diagnostic) => Some(diagnostic),
+    })
+}
+
+impl Diagnostic {
+    /// The kind of report corresponding to this diagnostic's level.
+    pub fn kind(&self) -> ReportKind {
+        match self.level.as_str() {
+            "error" | "error: internal compiler error" => ReportKind::Error,
+            "warning" => ReportKind::Warning,
+            _ => ReportKind::Advice,
+        }
+    }
+
+    /// Convert this diagnostic into a [`Report`].
+    ///
+    /// Primary spans are labelled in the color of the report kind and secondary spans in blue. Children without spans
+    /// become notes or helps, suggestions become helps that include the suggested code, and the spans of any other
+    /// children become further labels.
+    ///
+    /// rustc describes spans in bytes, so the report always uses [`IndexType::Byte`], whatever `config` says.
+    pub fn to_report(&self, config: Config) -> Report<RustcSpan<'_>> {
+        let config = config.with_index_type(IndexType::Byte);
+        let kind = self.kind();
+        let primary_color = kind.get_color(&config).unwrap_or(Color::Primary);
+
+        let span = self
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .or_else(|| self.spans.first())
+            .map_or((Path::new(""), 0..0), to_span);
+
+        let mut builder = Report::build(kind, span)
+            .with_config(config)
+            .with_message(&self.message);
+        builder.set_code(self.code.as_ref().map(|code| &code.code));
+
+        builder.add_labels(self.spans.iter().map(|span| {
+            let label = Label::new(to_span(span)).with_color(if span.is_primary {
+                primary_color
+            } else {
+                Color::Blue
+            });
+            match &span.label {
+                Some(msg) => label.with_message(msg),
+                None => label,
+            }
+        }));
+
+        for child in &self.children {
+            let replacements = child
+                .spans
+                .iter()
+                .filter_map(|span| span.suggested_replacement.as_deref())
+                .filter(|replacement| !replacement.is_empty())
+                .collect::<Vec<_>>();
+            if !replacements.is_empty() {
+                builder.add_help(format!(
+                    "{}: `{}`",
+                    child.message,
+                    replacements.join("`, `")
+                ));
+            } else if child
+                .spans
+                .iter()
+                .any(|span| span.suggested_replacement.is_some())
+            {
+                // Suggestions that only remove code have nothing more to show than their message
+                builder.add_help(&child.message);
+            } else if !child.spans.is_empty() {
+                builder.add_labels(child.spans.iter().map(|span| {
+                    Label::new(to_span(span))
+                        .with_color(Color::Blue)
+                        .with_message(span.label.as_ref().unwrap_or(&child.message))
+                }));
+            } else if child.level == "help" {
+                builder.add_help(&child.message);
+            } else {
+                builder.add_note(&child.message);
+            }
+        }
+
+        builder.finish()
+    }
+}
+
+fn to_span(span: &DiagnosticSpan) -> RustcSpan<'_> {
+    (
+        Path::new(&span.file_name),
+        span.byte_start..span.byte_end.max(span.byte_start),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SectionKind;
+
+    const MISMATCHED_TYPES: &str = r#"{"reason":"compiler-message","package_id":"demo 0.1.0","manifest_path":"/demo/Cargo.toml","target":{"kind":["bin"],"name":"demo","src_path":"/demo/src/main.rs"},"message":{"rendered":"error[E0308]: mismatched types\n","$message_type":"diagnostic","children":[{"children":[],"code":null,"level":"help","message":"try using a conversion method","rendered":null,"spans":[{"byte_end":34,"byte_start":34,"column_end":19,"column_start":19,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":null,"line_end":2,"line_start":2,"suggested_replacement":".to_string()","suggestion_applicability":"MaybeIncorrect","text":[]}]},{"children":[],"code":null,"level":"note","message":"expected type `String`","rendered":null,"spans":[]}],"code":{"code":"E0308","explanation":"Expected type did not match the received type.\n"},"level":"error","message":"mismatched types","spans":[{"byte_end":34,"byte_start":27,"column_end":19,"column_start":12,"expansion":null,"file_name":"src/main.rs","is_primary":true,"label":"expected `String`, found `&str`","line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]},{"byte_end":24,"byte_start":18,"column_end":21,"column_start":15,"expansion":null,"file_name":"src/main.rs","is_primary":false,"label":"expected due to this","line_end":2,"line_start":2,"suggested_replacement":null,"suggestion_applicability":null,"text":[]}]}}"#;
+
+    #[test]
+    fn cargo_compiler_message() {
+        let diagnostic = parse_message(MISMATCHED_TYPES).unwrap().unwrap();
+        let report = diagnostic.to_report(Config::default().with_color(false));
+
+        assert_eq!(report.kind, ReportKind::Error);
+        assert_eq!(report.code.as_deref(), Some("E0308"));
+        assert_eq!(report.span, (Path::new("src/main.rs"), 27..34));
+        assert_eq!(report.labels.len(), 2);
+        let [help, note] = &report.sections[..] else {
+            panic!("expected a help and a note, found {:?}", report.sections);
+        };
+        assert_eq!(help.kind, SectionKind::Help);
+        assert_eq!(
+            help.items,
+            ["try using a conversion method: `.to_string()`"]
+        );
+        assert_eq!(note.kind, SectionKind::Note);
+        assert_eq!(note.items, ["expected type `String`"]);
+    }
+
+    #[test]
+    fn non_diagnostic_messages() {
+        assert!(
+            parse_message(r#"{"reason":"build-finished","success":false}"#)
+                .unwrap()
+                .is_none()
+        );
+        assert!(parse_message(
+            r#"{"$message_type":"artifact","artifact":"x.rmeta","emit":"metadata"}"#
+        )
+        .unwrap()
+        .is_none());
+        assert!(parse_message("   Compiling demo v0.1.0").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+
+use super::Line;
+
+/// The number of characters between checkpoints.
+const INTERVAL: usize = 64;
+
+/// Sampled positions of characters within the lines of a source that are not pure ASCII, so that converting between
+/// character and byte offsets only needs to count the characters since the nearest checkpoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct CharIndex {
+    /// Character and byte offsets of checkpoints, in order. Each line that is not pure ASCII has a checkpoint at its
+    /// start, and then one every [`INTERVAL`] characters.
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl CharIndex {
+    pub(super) fn new(text: &str, lines: &[Line]) -> Self {
+        let mut index = Self::default();
+        index.add_lines(text, lines);
+        index
+    }
+
+    fn add_lines(&mut self, text: &str, lines: &[Line]) {
+        for line in lines.iter().filter(|line| line.char_len != line.byte_len) {
+            self.checkpoints.extend(
+                text[line.byte_span()]
+                    .char_indices()
+                    .step_by(INTERVAL)
+                    .enumerate()
+                    .map(|(i, (byte, _))| (line.offset + i * INTERVAL, line.byte_offset + byte)),
+            );
+        }
+    }
+
+    /// Update the index after an edit, which replaced the lines that started in `old` (a span of character offsets)
+    /// with `new_lines`, and moved the lines after them by the given number of characters and bytes.
+    pub(super) fn splice(
+        &mut self,
+        text: &str,
+        old: Range<usize>,
+        new_lines: &[Line],
+        (old_chars, new_chars): (usize, usize),
+        (old_bytes, new_bytes): (usize, usize),
+    ) {
+        let start = self.checkpoints.partition_point(|&(c, _)| c < old.start);
+        let end = self.checkpoints.partition_point(|&(c, _)| c < old.end);
+        let moved: Vec<_> = self.checkpoints[end..]
+            .iter()
+            .map(|&(c, b)| (c + new_chars - old_chars, b + new_bytes - old_bytes))
+            .collect();
+        self.checkpoints.truncate(start);
+        self.add_lines(text, new_lines);
+        self.checkpoints.extend(moved);
+    }
+
+    /// Find the character and byte columns of the last checkpoint in the line at or before the given character column.
+    pub(super) fn before_char(&self, line: Line, col_idx: usize) -> (usize, usize) {
+        let idx = self
+            .checkpoints
+            .partition_point(|&(c, _)| c <= line.offset + col_idx);
+        self.column(line, idx)
+    }
+
+    /// Find the character and byte columns of the last checkpoint in the line at or before the given byte column.
+    pub(super) fn before_byte(&self, line: Line, byte_col: usize) -> (usize, usize) {
+        let idx = self
+            .checkpoints
+            .partition_point(|&(_, b)| b <= line.byte_offset + byte_col);
+        self.column(line, idx)
+    }
+
+    fn column(&self, line: Line, idx: usize) -> (usize, usize) {
+        match idx.checked_sub(1).map(|idx| self.checkpoints[idx]) {
+            Some((c, b)) if c >= line.offset => (c - line.offset, b - line.byte_offset),
+            _ => (0, 0),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+
+use std::borrow::Borrow;
+
+/// A [`Cache`] that fetches [`Source`]s from one cache, and then from another if the first fails.
+///
+/// IDs are displayed by the first cache, unless it does not display them.
+#[derive(Default, Debug, Clone)]
+pub struct FallbackCache<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> FallbackCache<A, B> {
+    /// Create a new [`FallbackCache`] that tries `first`, then `second`.
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<Id, A, B> Cache<Id> for FallbackCache<A, B>
+where
+    Id: ?Sized,
+    A: Cache<Id>,
+    B: Cache<Id, Storage = A::Storage>,
+{
+    type Storage = A::Storage;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<Self::Storage>, impl fmt::Debug> {
+        match self.first.fetch(id) {
+            Ok(src) => Ok(src),
+            Err(first) => self.second.fetch(id).map_err(|second| (first, second)),
+        }
+    }
+    fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
+        match self.first.display(id) {
+            Some(display) => Some(Box::new(display) as Box<dyn fmt::Display + 'a>),
+            None => Some(Box::new(self.second.display(id)?)),
+        }
+    }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        self.first
+            .absolute_path(id)
+            .or_else(|| self.second.absolute_path(id))
+    }
+}
+
+/// A [`Cache`] that converts IDs with the provided function before fetching [`Source`]s from another cache.
+///
+/// This allows a cache to be used with spans whose source IDs are of a different type, such as spans that refer to
+/// files by an index into a list of their names.
+#[derive(Debug, Clone)]
+pub struct MapIdCache<C, F> {
+    cache: C,
+    map: F,
+}
+
+impl<C, F> MapIdCache<C, F> {
+    /// Create a new [`MapIdCache`] that fetches from `cache` using the IDs given by `map`.
+    pub fn new(cache: C, map: F) -> Self {
+        Self { cache, map }
+    }
+}
+
+impl<Id, J, C, F> Cache<Id> for MapIdCache<C, F>
+where
+    Id: ?Sized,
+    C: Cache<J>,
+    F: Fn(&Id) -> J,
+{
+    type Storage = C::Storage;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<Self::Storage>, impl fmt::Debug> {
+        let id = (self.map)(id);
+        // The error may borrow the mapped ID, so it can't outlive this call.
+        self.cache.fetch(&id).map_err(|e| Message(format!("{e:?}")))
+    }
+    fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
+        let id = (self.map)(id);
+        let display = self.cache.display(&id).map(|display| display.to_string());
+        display
+    }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        self.cache.absolute_path(&(self.map)(id))
+    }
+}
+
+/// A [`Cache`] of in-memory [`Source`]s that shadow those of another cache.
+///
+/// This is useful for showing the unsaved contents of files that are open in an editor, for example. IDs are always
+/// displayed by the underlying cache.
+#[derive(Debug, Clone)]
+pub struct OverlayCache<C, K, I>
+where
+    I: AsRef<str>,
+{
+    cache: C,
+    sources: HashMap<K, Source<I>>,
+}
+
+impl<C, K, I> OverlayCache<C, K, I>
+where
+    I: AsRef<str>,
+{
+    /// Create a new [`OverlayCache`] over the given cache, with no sources of its own.
+    pub fn new(cache: C) -> Self {
+        Self {
+            cache,
+            sources: HashMap::default(),
+        }
+    }
+
+    /// Add a source that shadows the one with the same ID in the underlying cache, returning the source that it
+    /// replaces in the overlay, if any.
+    pub fn insert<S: Into<Source<I>>>(&mut self, id: K, source: S) -> Option<Source<I>>
+    where
+        K: Eq + Hash,
+    {
+        self.sources.insert(id, source.into())
+    }
+
+    /// Remove the source with the given ID from the overlay, revealing the one in the underlying cache.
+    pub fn remove<Id>(&mut self, id: &Id) -> Option<Source<I>>
+    where
+        Id: Eq + Hash + ?Sized,
+        K: Eq + Hash + Borrow<Id>,
+    {
+        self.sources.remove(id)
+    }
+
+    /// Get the underlying cache.
+    pub fn inner(&self) -> &C {
+        &self.cache
+    }
+
+    /// Get the underlying cache mutably, such as to invalidate a [`FileCache`] entry.
+    pub fn inner_mut(&mut self) -> &mut C {
+        &mut self.cache
+    }
+}
+
+impl<Id, C, K, I> Cache<Id> for OverlayCache<C, K, I>
+where
+    Id: Eq + Hash + ?Sized,
+    K: Eq + Hash + Borrow<Id>,
+    I: AsRef<str>,
+    C: Cache<Id, Storage = I>,
+{
+    type Storage = I;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<I>, impl fmt::Debug> {
+        match self.sources.get(id) {
+            Some(src) => Ok(src),
+            None => self.cache.fetch(id),
+        }
+    }
+    fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
+        self.cache.display(id)
+    }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        self.cache.absolute_path(id)
+    }
+}
+
+/// An error message, which is printed as-is rather than quoted.
+pub(super) struct Message(pub(super) String);
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,151 @@
+/// The encoding used to decode the bytes of a source, with [`Source::decode`](crate::Source::decode) or
+/// [`FileCache::with_encoding`](crate::FileCache::with_encoding).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8. Sources that are not valid UTF-8 fail to decode.
+    #[default]
+    Utf8,
+    /// UTF-8, with each invalid sequence of bytes replaced by `U+FFFD REPLACEMENT CHARACTER`.
+    Utf8Lossy,
+    /// ISO-8859-1 (Latin-1), in which every byte is a character.
+    Latin1,
+    /// UTF-16, which is big-endian if the source starts with a big-endian byte order mark and little-endian otherwise.
+    ///
+    /// The byte order mark is not part of the decoded text, and unpaired surrogates are replaced by
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    Utf16,
+}
+
+/// A mapping from byte offsets in the original bytes of a decoded source to byte offsets in its text.
+#[derive(Clone, Debug)]
+pub(super) struct ByteMap {
+    segments: Vec<Segment>,
+    len: usize,
+}
+
+/// A run of the original bytes starting at `original`, which was decoded to text starting at `decoded`.
+///
+/// Identical runs were copied byte-for-byte, so offsets within them map one-to-one. Other runs are a single
+/// character, so every offset within them maps to its start.
+#[derive(Copy, Clone, Debug)]
+struct Segment {
+    original: usize,
+    decoded: usize,
+    identical: bool,
+}
+
+impl ByteMap {
+    /// Get the byte offset in the decoded text of the given offset in the original bytes.
+    pub(super) fn decoded_offset(&self, offset: usize) -> Option<usize> {
+        if offset > self.len {
+            return None;
+        }
+        let idx = self
+            .segments
+            .partition_point(|segment| segment.original <= offset);
+        Some(match idx.checked_sub(1).map(|idx| self.segments[idx]) {
+            Some(segment) if segment.identical => segment.decoded + offset - segment.original,
+            Some(segment) => segment.decoded,
+            // A leading byte order mark is not decoded to anything.
+            None => 0,
+        })
+    }
+}
+
+struct Decoder {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl Decoder {
+    fn push(&mut self, original: usize, decoded: &str, identical: bool) {
+        let contiguous = self.segments.last().is_some_and(|last| {
+            last.identical && original - last.original == self.text.len() - last.decoded
+        });
+        if !(identical && contiguous) {
+            self.segments.push(Segment {
+                original,
+                decoded: self.text.len(),
+                identical,
+            });
+        }
+        self.text.push_str(decoded);
+    }
+
+    fn push_char(&mut self, original: usize, c: char, identical: bool) {
+        self.push(original, c.encode_utf8(&mut [0; 4]), identical);
+    }
+
+    fn finish(mut self, len: usize) -> (String, Option<ByteMap>) {
+        // Offsets at the very end of the original bytes map to the end of the text.
+        self.push(len, "", true);
+        let map = ByteMap {
+            segments: self.segments,
+            len,
+        };
+        (self.text, Some(map))
+    }
+}
+
+/// Decode `bytes` to text, along with a mapping from their offsets to those of the text if they differ.
+pub(super) fn decode(
+    bytes: Vec<u8>,
+    encoding: Encoding,
+) -> Result<(String, Option<ByteMap>), std::string::FromUtf8Error> {
+    let mut decoder = Decoder {
+        text: String::with_capacity(bytes.len()),
+        segments: Vec::new(),
+    };
+    match encoding {
+        Encoding::Utf8 => return String::from_utf8(bytes).map(|text| (text, None)),
+        Encoding::Utf8Lossy => {
+            let bytes = match String::from_utf8(bytes) {
+                Ok(text) => return Ok((text, None)),
+                Err(e) => e.into_bytes(),
+            };
+            let mut original = 0;
+            for chunk in bytes.utf8_chunks() {
+                decoder.push(original, chunk.valid(), true);
+                original += chunk.valid().len();
+                if !chunk.invalid().is_empty() {
+                    decoder.push_char(original, char::REPLACEMENT_CHARACTER, false);
+                    original += chunk.invalid().len();
+                }
+            }
+            return Ok(decoder.finish(bytes.len()));
+        }
+        Encoding::Latin1 => {
+            if bytes.is_ascii() {
+                return String::from_utf8(bytes).map(|text| (text, None));
+            }
+            for (original, &byte) in bytes.iter().enumerate() {
+                decoder.push_char(original, char::from(byte), byte.is_ascii());
+            }
+        }
+        Encoding::Utf16 => {
+            let (from_bytes, start): (fn([u8; 2]) -> u16, _) = match bytes.get(..2) {
+                Some([0xFE, 0xFF]) => (u16::from_be_bytes, 2),
+                Some([0xFF, 0xFE]) => (u16::from_le_bytes, 2),
+                _ => (u16::from_le_bytes, 0),
+            };
+            let units = bytes[start..]
+                .chunks_exact(2)
+                .map(|unit| from_bytes([unit[0], unit[1]]));
+            let mut original = start;
+            for c in char::decode_utf16(units) {
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                decoder.push_char(original, c, false);
+                original += if c == char::REPLACEMENT_CHARACTER {
+                    2
+                } else {
+                    c.len_utf16() * 2
+                };
+            }
+            // A trailing odd byte cannot be a code unit.
+            if original < bytes.len() {
+                decoder.push_char(original, char::REPLACEMENT_CHARACTER, false);
+            }
+        }
+    }
+    Ok(decoder.finish(bytes.len()))
+}
@@ -0,0 +1,186 @@
+use super::*;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use memmap2::Mmap;
+
+/// The text of a memory-mapped file, used as the storage of an [`MmapFileCache`].
+///
+/// Unless the cache checks files up front (see [`MmapFileCache::with_strict_utf8`]), the text is only checked to be
+/// UTF-8 as far as it is read, so that reading the start of a large file doesn't touch the rest of it. If a file has
+/// invalid UTF-8, wherever it is, its text ends before the first invalid byte, and a warning is printed to stderr when
+/// that byte is reached.
+#[derive(Debug)]
+pub struct MappedText {
+    map: Mmap,
+    /// The path that the file was opened from, for warnings.
+    path: PathBuf,
+    /// The number of bytes at the start of the map that are known to be valid UTF-8.
+    pub(super) checked: AtomicUsize,
+    /// Whether the checked bytes are followed by invalid UTF-8, so that they are the whole text.
+    invalid: AtomicBool,
+}
+
+impl MappedText {
+    /// Map the file at the given path, checking all of it up front if `strict` is set.
+    fn open(path: &Path, strict: bool) -> Result<Self, Error> {
+        let file = fs::File::open(path)?;
+        // SAFETY: the caller of `MmapFileCache::new` promised that cached files are not changed while they are mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        let checked = if strict {
+            std::str::from_utf8(&map)
+                .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+            map.len()
+        } else {
+            0
+        };
+        Ok(Self {
+            map,
+            path: path.to_path_buf(),
+            checked: AtomicUsize::new(checked),
+            invalid: AtomicBool::new(false),
+        })
+    }
+
+    /// Get the text up to at least `end` bytes, or all of it if it is shorter, and whether that is all of it.
+    fn read_to(&self, end: usize) -> (&str, bool) {
+        let end = end.min(self.map.len());
+        let checked = self.checked.load(Ordering::Acquire);
+        if checked < end && !self.invalid.load(Ordering::Acquire) {
+            let (valid, invalid) = match std::str::from_utf8(&self.map[checked..end]) {
+                Ok(_) => (end, false),
+                // A character cut off by `end` is checked when more of the text is read, unless the file ends there.
+                Err(e) => (
+                    checked + e.valid_up_to(),
+                    e.error_len().is_some() || end == self.map.len(),
+                ),
+            };
+            // Other threads may have checked further in the meantime.
+            self.checked.fetch_max(valid, Ordering::AcqRel);
+            if invalid && !self.invalid.swap(true, Ordering::AcqRel) {
+                eprintln!(
+                    "{} is not valid UTF-8 from byte {valid}, so only the text before it is shown",
+                    self.path.display()
+                );
+            }
+        }
+        let invalid = self.invalid.load(Ordering::Acquire);
+        let checked = self.checked.load(Ordering::Acquire);
+        // SAFETY: the first `checked` bytes of the map were checked to be valid UTF-8.
+        let text = unsafe { std::str::from_utf8_unchecked(&self.map[..checked]) };
+        (text, invalid || checked == self.map.len())
+    }
+}
+
+impl AsRef<str> for MappedText {
+    fn as_ref(&self) -> &str {
+        self.read_to(usize::MAX).0
+    }
+}
+
+/// A [`Cache`] that fetches [`Source`]s from the filesystem by mapping them into memory.
+///
+/// Files are not read up front, and their lines are only indexed as far as the highest offset that is looked up (see
+/// [`Source::lazy`]). This makes it much cheaper than [`FileCache`] for large files, such as generated code, of which
+/// only a few lines are shown.
+///
+/// Files must not be changed while they are in the cache: see [`MmapFileCache::new`].
+#[derive(Debug)]
+pub struct MmapFileCache {
+    files: HashMap<PathBuf, Source<MappedText>>,
+    paths: PathPolicy,
+    strict_utf8: bool,
+}
+
+impl MmapFileCache {
+    /// Create a new, empty [`MmapFileCache`].
+    ///
+    /// # Safety
+    ///
+    /// Files must not be written to or truncated, by this process or any other, while they are in the cache. The text
+    /// of a mapped file changes along with the file, so changes can make it invalid UTF-8 after it has been checked, and
+    /// reading a mapped file that has been truncated crashes the process (with `SIGBUS` on Unix). Use
+    /// [`MmapFileCache::invalidate`] to remove a file from the cache before it changes, or use [`FileCache`], which
+    /// copies files into memory, for files that may change.
+    pub unsafe fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+            paths: PathPolicy::default(),
+            strict_utf8: false,
+        }
+    }
+
+    /// Read relative paths from the given directory, rather than the current directory.
+    ///
+    /// The root is also used by [`PathDisplay::RelativeToRoot`].
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.paths.root = Some(root.into());
+        self
+    }
+
+    /// Specify how the paths of files are displayed in reports.
+    ///
+    /// If unspecified, this is [`PathDisplay::Verbatim`].
+    pub fn with_path_display(mut self, display: PathDisplay) -> Self {
+        self.paths.display = display;
+        self
+    }
+
+    /// Check that each file is valid UTF-8 when it is fetched, and fail to fetch it if not.
+    ///
+    /// This reads the whole of every file that is fetched. If unspecified, files are only checked as far as they are
+    /// read, and the text of a file with invalid UTF-8 ends before it (see [`MappedText`]).
+    pub fn with_strict_utf8(mut self, strict_utf8: bool) -> Self {
+        self.strict_utf8 = strict_utf8;
+        self
+    }
+
+    /// Remove the given file from the cache, unmapping it.
+    ///
+    /// Returns `true` if the file was in the cache.
+    pub fn invalidate<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        self.files.remove(path.as_ref()).is_some()
+    }
+
+    /// Remove and unmap every file in the cache.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
+
+impl Cache<Path> for MmapFileCache {
+    type Storage = MappedText;
+
+    fn fetch(&mut self, path: &Path) -> Result<&Source<MappedText>, impl fmt::Debug> {
+        Ok::<_, Error>(match self.files.entry(path.to_path_buf()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Source {
+                read_to: Some(MappedText::read_to),
+                ..Source::lazy(MappedText::open(
+                    &self.paths.resolve(path),
+                    self.strict_utf8,
+                )?)
+            }),
+        })
+    }
+    fn display<'a>(&self, path: &'a Path) -> Option<impl fmt::Display + 'a> {
+        Some(self.paths.display(path))
+    }
+    fn absolute_path(&self, path: &Path) -> Option<PathBuf> {
+        std::path::absolute(self.paths.resolve(path)).ok()
+    }
+}
+
+impl Cache<&Path> for MmapFileCache {
+    type Storage = MappedText;
+
+    fn fetch(&mut self, path: &&Path) -> Result<&Source<MappedText>, impl fmt::Debug> {
+        Cache::<Path>::fetch(self, *path)
+    }
+    fn display<'a>(&self, path: &'a &Path) -> Option<impl fmt::Display + 'a> {
+        Cache::<Path>::display(self, *path)
+    }
+    fn absolute_path(&self, path: &&Path) -> Option<PathBuf> {
+        Cache::<Path>::absolute_path(self, *path)
+    }
+}
@@ -1,12 +1,32 @@
 use super::*;
+mod char_index;
+mod combinators;
+mod decode;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod path;
+mod shared;
 #[cfg(test)]
 mod tests;
 
+use char_index::CharIndex;
+pub use combinators::{FallbackCache, MapIdCache, OverlayCache};
+use decode::ByteMap;
+pub use decode::Encoding;
+#[cfg(feature = "mmap")]
+pub use mmap::{MappedText, MmapFileCache};
+pub use path::PathDisplay;
+use path::PathPolicy;
+pub use shared::SharedCache;
+
 use std::io::Error;
+use std::string::FromUtf8Error;
 use std::{
     collections::{hash_map::Entry, HashMap},
     fs,
     path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    time::SystemTime,
 };
 
 /// A trait implemented by [`Source`] caches.
@@ -25,6 +45,15 @@ pub trait Cache<Id: ?Sized> {
     ///
     /// This function may make use of attributes from the [`Fmt`] trait.
     fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a>;
+
+    /// The absolute path of the given ID on the filesystem, if it has one.
+    ///
+    /// This is used to link to sources when [`Config::with_hyperlinks`](crate::Config::with_hyperlinks) is enabled.
+    /// By default, sources have no path.
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        let _ = id;
+        None
+    }
 }
 
 impl<C: Cache<Id>, Id: ?Sized> Cache<Id> for &mut C {
@@ -36,6 +65,9 @@ impl<C: Cache<Id>, Id: ?Sized> Cache<Id> for &mut C {
     fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
         C::display(self, id)
     }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        C::absolute_path(self, id)
+    }
 }
 
 impl<C: Cache<Id>, Id: ?Sized> Cache<Id> for Box<C> {
@@ -47,6 +79,9 @@ impl<C: Cache<Id>, Id: ?Sized> Cache<Id> for Box<C> {
     fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
         C::display(self, id)
     }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        C::absolute_path(self, id)
+    }
 }
 
 /// A type representing a single line of a [`Source`].
@@ -81,7 +116,7 @@ impl Line {
 
     /// Get the byte offset span of this line in the original [`Source`]. This can be used to
     /// directly slice into its source text.
-    fn byte_span(&self) -> Range<usize> {
+    pub fn byte_span(&self) -> Range<usize> {
         self.byte_offset..self.byte_offset + self.byte_len
     }
 }
@@ -89,20 +124,53 @@ impl Line {
 /// A type representing a single source that may be referred to by [`Span`]s.
 ///
 /// In most cases, a source is a single input file.
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct Source<I: AsRef<str> = String> {
     text: I,
-    lines: Vec<Line>,
-    len: usize,
-    byte_len: usize,
+    lines: LineIndex,
     display_line_offset: usize,
+    byte_map: Option<ByteMap>,
+    char_index: Option<CharIndex>,
+    /// Reads part of the text, for storage that only checks its text as far as it is read.
+    read_to: Option<ReadTo<I>>,
 }
 
+/// Get the text of some storage up to at least the given byte offset, or all of it if it is shorter, and whether that is
+/// all of it.
+type ReadTo<I> = for<'a> fn(&'a I, usize) -> (&'a str, bool);
+
 impl<I: AsRef<str>> Source<I> {
     /// Get the full text of this source file.
     pub fn text(&self) -> &str {
         self.text.as_ref()
     }
+
+    /// Generate a [`Source`] whose lines are only found when they are first needed.
+    ///
+    /// Looking up a line or an offset only scans the text as far as that line, so this is much cheaper than
+    /// [`Source::from`] for large inputs of which only a few lines are displayed. Functions that need every line,
+    /// such as [`Source::len`] and [`Source::lines`], still scan the whole text.
+    pub fn lazy(input: I) -> Self {
+        Self {
+            text: input,
+            lines: LineIndex::Lazy {
+                scanned: Mutex::default(),
+                complete: OnceLock::new(),
+            },
+            display_line_offset: 0,
+            byte_map: None,
+            char_index: None,
+            read_to: None,
+        }
+    }
+
+    /// Get the text up to at least the given byte offset, or all of it if it is shorter, and whether that is all of it.
+    fn text_to(&self, end: usize) -> (&str, bool) {
+        match self.read_to {
+            Some(read_to) => read_to(&self.text, end),
+            None => (self.text.as_ref(), true),
+        }
+    }
 }
 
 impl<I: AsRef<str>> From<I> for Source<I> {
@@ -110,63 +178,290 @@ impl<I: AsRef<str>> From<I> for Source<I> {
     ///
     /// Note that this function can be expensive for long strings. Use an implementor of [`Cache`] where possible.
     fn from(input: I) -> Self {
-        // `input.split_inclusive()` will not iterate at all,
-        // but an empty input still ought to count as a single empty line.
-        if input.as_ref().is_empty() {
-            return Self {
-                text: input,
-                lines: vec![Line {
-                    offset: 0,
-                    char_len: 0,
-                    byte_offset: 0,
-                    byte_len: 0,
-                }],
-                len: 0,
-                byte_len: 0,
-                display_line_offset: 0,
-            };
+        let mut lines = Vec::new();
+        scan_lines(input.as_ref(), &mut lines, |_| false);
+        Self {
+            text: input,
+            lines: LineIndex::Eager(lines),
+            display_line_offset: 0,
+            byte_map: None,
+            char_index: None,
+            read_to: None,
         }
+    }
+}
 
-        let mut char_offset = 0;
-        let mut byte_offset = 0;
-        let mut lines = Vec::new();
+impl<I: AsRef<str> + Clone> Clone for Source<I> {
+    fn clone(&self) -> Self {
+        Self {
+            text: self.text.clone(),
+            lines: self.lines.clone(),
+            display_line_offset: self.display_line_offset,
+            byte_map: self.byte_map.clone(),
+            char_index: self.char_index.clone(),
+            read_to: self.read_to,
+        }
+    }
+}
+
+// The line index is derived from the text, so it takes no part in comparisons.
+impl<I: AsRef<str> + PartialEq> PartialEq for Source<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text && self.display_line_offset == other.display_line_offset
+    }
+}
+
+impl<I: AsRef<str> + Eq> Eq for Source<I> {}
 
-        const SEPARATORS: [char; 7] = [
-            '\r',       // Carriage return
-            '\n',       // Line feed
-            '\x0B',     // Vertical tab
-            '\x0C',     // Form feed
-            '\u{0085}', // Next line
-            '\u{2028}', // Line separator
-            '\u{2029}', // Paragraph separator
-        ];
-        let mut remaining = input.as_ref().split_inclusive(SEPARATORS).peekable();
-        while let Some(line) = remaining.next() {
-            let mut byte_len = line.len();
-            let mut char_len = line.chars().count();
-            // Handle CRLF as a single terminator.
-            if line.ends_with('\r') && remaining.next_if_eq(&"\n").is_some() {
-                byte_len += 1;
-                char_len += 1;
+impl<I: AsRef<str> + Hash> Hash for Source<I> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+        self.display_line_offset.hash(state);
+    }
+}
+
+/// The [`Line`]s of a [`Source`], which are either all found up front or scanned for on demand.
+#[derive(Debug)]
+enum LineIndex {
+    Eager(Vec<Line>),
+    Lazy {
+        /// The lines found so far.
+        scanned: Mutex<Vec<Line>>,
+        /// Every line, once the whole text has been scanned.
+        complete: OnceLock<Vec<Line>>,
+    },
+}
+
+impl LineIndex {
+    /// Call `f` with the lines of the text read by `text_to` (see [`Source::text_to`]), scanning for more until `needed`
+    /// returns `true` or the text runs out.
+    fn with<'a, R>(
+        &self,
+        text_to: impl Fn(usize) -> (&'a str, bool),
+        needed: impl FnMut(&[Line]) -> bool,
+        f: impl FnOnce(&[Line]) -> R,
+    ) -> R {
+        match self {
+            LineIndex::Eager(lines) => f(lines),
+            LineIndex::Lazy { scanned, complete } => {
+                if let Some(lines) = complete.get() {
+                    return f(lines);
+                }
+                let mut scanned = scanned.lock().unwrap_or_else(PoisonError::into_inner);
+                // Another thread may have finished the scan while we waited for the lock.
+                if let Some(lines) = complete.get() {
+                    return f(lines);
+                }
+                scan_lines_to(text_to, &mut scanned, needed);
+                f(&scanned)
             }
-            lines.push(Line {
-                offset: char_offset,
-                char_len,
-                byte_offset,
-                byte_len,
-            });
-
-            char_offset += char_len;
-            byte_offset += byte_len;
         }
+    }
 
-        Self {
-            text: input,
-            lines,
-            len: char_offset,
-            byte_len: byte_offset,
-            display_line_offset: 0,
+    /// Get every line of `text`.
+    fn all(&self, text: &str) -> &[Line] {
+        match self {
+            LineIndex::Eager(lines) => lines,
+            LineIndex::Lazy { scanned, complete } => {
+                if let Some(lines) = complete.get() {
+                    return lines;
+                }
+                let mut scanned = scanned.lock().unwrap_or_else(PoisonError::into_inner);
+                complete.get_or_init(|| {
+                    scan_lines(text, &mut scanned, |_| false);
+                    std::mem::take(&mut *scanned)
+                })
+            }
+        }
+    }
+}
+
+impl Clone for LineIndex {
+    fn clone(&self) -> Self {
+        match self {
+            LineIndex::Eager(lines) => LineIndex::Eager(lines.clone()),
+            LineIndex::Lazy { scanned, complete } => match complete.get() {
+                Some(lines) => LineIndex::Eager(lines.clone()),
+                None => LineIndex::Lazy {
+                    scanned: Mutex::new(
+                        scanned
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .clone(),
+                    ),
+                    complete: OnceLock::new(),
+                },
+            },
+        }
+    }
+}
+
+/// The character, or pair of characters, that ends a line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// A line feed, `\n`.
+    Lf,
+    /// A carriage return followed by a line feed, `\r\n`.
+    CrLf,
+    /// A carriage return, `\r`.
+    Cr,
+    /// A vertical tab, `\x0B`.
+    VerticalTab,
+    /// A form feed, `\x0C`.
+    FormFeed,
+    /// A next line character, `U+0085`.
+    NextLine,
+    /// A line separator, `U+2028`.
+    LineSeparator,
+    /// A paragraph separator, `U+2029`.
+    ParagraphSeparator,
+}
+
+impl LineEnding {
+    /// Get the text of this line ending.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+            LineEnding::VerticalTab => "\x0B",
+            LineEnding::FormFeed => "\x0C",
+            LineEnding::NextLine => "\u{0085}",
+            LineEnding::LineSeparator => "\u{2028}",
+            LineEnding::ParagraphSeparator => "\u{2029}",
+        }
+    }
+
+    /// Get the number of characters in this line ending.
+    pub fn char_len(&self) -> usize {
+        self.as_str().chars().count()
+    }
+
+    fn of(line_text: &str) -> Option<Self> {
+        if line_text.ends_with("\r\n") {
+            return Some(LineEnding::CrLf);
+        }
+        Some(match line_text.chars().next_back()? {
+            '\n' => LineEnding::Lf,
+            '\r' => LineEnding::Cr,
+            '\x0B' => LineEnding::VerticalTab,
+            '\x0C' => LineEnding::FormFeed,
+            '\u{0085}' => LineEnding::NextLine,
+            '\u{2028}' => LineEnding::LineSeparator,
+            '\u{2029}' => LineEnding::ParagraphSeparator,
+            _ => return None,
+        })
+    }
+}
+
+/// The style of line endings used throughout a [`Source`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineEndings {
+    /// The source has no line endings, because it has a single line.
+    None,
+    /// Every line ending in the source is the same.
+    Uniform(LineEnding),
+    /// The source uses more than one kind of line ending.
+    Mixed,
+}
+
+const SEPARATORS: [char; 7] = [
+    '\r',       // Carriage return
+    '\n',       // Line feed
+    '\x0B',     // Vertical tab
+    '\x0C',     // Form feed
+    '\u{0085}', // Next line
+    '\u{2028}', // Line separator
+    '\u{2029}', // Paragraph separator
+];
+
+/// Scan the line of `text` that starts at the given offsets, which must be before the end of the text.
+fn scan_line(text: &str, char_offset: usize, byte_offset: usize) -> Line {
+    let rest = &text[byte_offset..];
+    let mut byte_len = rest
+        .char_indices()
+        .find(|(_, c)| SEPARATORS.contains(c))
+        .map_or(rest.len(), |(idx, c)| idx + c.len_utf8());
+    // Handle CRLF as a single terminator.
+    if rest[..byte_len].ends_with('\r') && rest[byte_len..].starts_with('\n') {
+        byte_len += 1;
+    }
+    Line {
+        offset: char_offset,
+        char_len: rest[..byte_len].chars().count(),
+        byte_offset,
+        byte_len,
+    }
+}
+
+/// The number of bytes of text that are read at a time when scanning lazily.
+const CHUNK: usize = 64 * 1024;
+
+/// Scan the text read by `text_to` (see [`Source::text_to`]) for the lines following those already in `lines`, as
+/// [`scan_lines`] does, but only reading as much of the text as is needed.
+fn scan_lines_to<'a>(
+    text_to: impl Fn(usize) -> (&'a str, bool),
+    lines: &mut Vec<Line>,
+    mut done: impl FnMut(&[Line]) -> bool,
+) {
+    let (mut char_offset, mut byte_offset) = lines.last().map_or((0, 0), |line| {
+        (
+            line.offset + line.char_len,
+            line.byte_offset + line.byte_len,
+        )
+    });
+    let mut chunk = CHUNK;
+    loop {
+        let (text, complete) = text_to(byte_offset + chunk);
+        if complete {
+            return scan_lines(text, lines, done);
+        }
+        let scanned = lines.len();
+        while !done(lines) {
+            let line = scan_line(text, char_offset, byte_offset);
+            // A line that reaches the end of the text read so far may carry on past it.
+            if line.byte_offset + line.byte_len >= text.len() {
+                break;
+            }
+            char_offset += line.char_len;
+            byte_offset += line.byte_len;
+            lines.push(line);
         }
+        if done(lines) {
+            return;
+        }
+        // Read further if a single line is longer than a chunk.
+        chunk = if lines.len() == scanned {
+            chunk * 2
+        } else {
+            CHUNK
+        };
+    }
+}
+
+/// Scan `text` for the lines following those already in `lines`, stopping early once `done` returns `true`.
+fn scan_lines(text: &str, lines: &mut Vec<Line>, mut done: impl FnMut(&[Line]) -> bool) {
+    let (mut char_offset, mut byte_offset) = lines.last().map_or((0, 0), |line| {
+        (
+            line.offset + line.char_len,
+            line.byte_offset + line.byte_len,
+        )
+    });
+    while byte_offset < text.len() && !done(lines) {
+        let line = scan_line(text, char_offset, byte_offset);
+        char_offset += line.char_len;
+        byte_offset += line.byte_len;
+        lines.push(line);
+    }
+
+    // An empty input still ought to count as a single empty line.
+    if lines.is_empty() {
+        lines.push(Line {
+            offset: 0,
+            char_len: 0,
+            byte_offset: 0,
+            byte_len: 0,
+        });
     }
 }
 
@@ -177,6 +472,17 @@ impl<I: AsRef<str>> Source<I> {
         self
     }
 
+    /// Index the positions of characters within lines that are not pure ASCII, so that converting between character
+    /// and byte offsets doesn't need to count every character from the start of the line.
+    ///
+    /// This speeds up [`IndexType::Byte`](crate::IndexType::Byte) spans and conversions such as
+    /// [`Source::offset_to_byte`] on long lines of multi-byte text, at the cost of two offsets for every 64 characters
+    /// of such lines. Building the index scans every line, even for [`Source::lazy`] sources.
+    pub fn with_char_index(mut self, char_index: bool) -> Self {
+        self.char_index = char_index.then(|| CharIndex::new(self.text.as_ref(), self.all_lines()));
+        self
+    }
+
     /// Get the offset added to printed line numbers
     pub fn display_line_offset(&self) -> usize {
         self.display_line_offset
@@ -184,12 +490,14 @@ impl<I: AsRef<str>> Source<I> {
 
     /// Get the length of the total number of characters in the source.
     pub fn len(&self) -> usize {
-        self.len
+        self.all_lines()
+            .last()
+            .map_or(0, |line| line.offset + line.char_len)
     }
 
     /// Returns `true` if this source contains no characters.
     pub fn is_empty(&self) -> bool {
-        self.len() == 0
+        self.text_to(1).0.is_empty()
     }
 
     /// Return an iterator over the characters in the source.
@@ -199,66 +507,119 @@ impl<I: AsRef<str>> Source<I> {
 
     /// Get access to a specific, zero-indexed [`Line`].
     pub fn line(&self, idx: usize) -> Option<Line> {
-        self.lines.get(idx).copied()
+        self.lines.with(
+            |end| self.text_to(end),
+            |lines| lines.len() > idx,
+            |lines| lines.get(idx).copied(),
+        )
     }
 
     /// Return an iterator over the [`Line`]s in this source.
     pub fn lines(&self) -> impl ExactSizeIterator<Item = Line> + '_ {
-        self.lines.iter().copied()
+        self.all_lines().iter().copied()
+    }
+
+    fn all_lines(&self) -> &[Line] {
+        self.lines.all(self.text.as_ref())
     }
 
     /// Get the line that the given offset appears on, and the line/column numbers of the offset.
     ///
     /// Note that the line/column numbers are zero-indexed.
     pub fn get_offset_line(&self, offset: usize) -> Option<Location> {
-        if offset <= self.len {
-            let idx = self
-                .lines
-                .binary_search_by_key(&offset, |line| line.offset)
-                .unwrap_or_else(|idx| idx.saturating_sub(1));
-            let line = self.line(idx)?;
-            assert!(
-                offset >= line.offset,
-                "offset = {}, line.offset = {}",
-                offset,
-                line.offset
-            );
-            Some(Location {
-                line,
-                line_idx: idx,
-                col_idx: offset - line.offset,
-            })
-        } else {
-            None
-        }
+        self.locate(offset).map(|location| Location {
+            col_idx: self.display_col(&location),
+            ..location
+        })
+    }
+
+    /// Like [`Source::get_offset_line`], but with a column that counts a byte order mark.
+    pub(crate) fn locate(&self, offset: usize) -> Option<Location> {
+        self.lines.with(
+            |end| self.text_to(end),
+            |lines| lines.last().is_some_and(|line| line.span().end > offset),
+            |lines| {
+                if offset > lines.last()?.span().end {
+                    return None;
+                }
+                let idx = lines
+                    .binary_search_by_key(&offset, |line| line.offset)
+                    .unwrap_or_else(|idx| idx.saturating_sub(1));
+                let line = *lines.get(idx)?;
+                assert!(
+                    offset >= line.offset,
+                    "offset = {}, line.offset = {}",
+                    offset,
+                    line.offset
+                );
+                Some(Location {
+                    line,
+                    line_idx: idx,
+                    col_idx: offset - line.offset,
+                })
+            },
+        )
     }
 
     /// Get the line that the given byte offset appears on, and the line/byte column numbers of the offset.
     ///
     /// Note that the line/column numbers are zero-indexed.
+    ///
+    /// For sources made with [`Source::decode`], the byte offset is into the original bytes, while the column is into
+    /// the decoded text of the line.
     pub fn get_byte_line(&self, byte_offset: usize) -> Option<Location> {
-        if byte_offset <= self.byte_len {
-            let idx = self
-                .lines
-                .binary_search_by_key(&byte_offset, |line| line.byte_offset)
-                .unwrap_or_else(|idx| idx.saturating_sub(1));
-            let line = self.line(idx)?;
-            assert!(
-                byte_offset >= line.byte_offset,
-                "byte_offset = {}, line.byte_offset = {}",
-                byte_offset,
-                line.byte_offset
-            );
-            Some(Location {
-                line,
-                line_idx: idx,
-                col_idx: byte_offset - line.byte_offset,
-            })
-        } else {
-            None
+        self.locate_byte(byte_offset).map(|location| Location {
+            col_idx: location.col_idx - self.bom_len(location.line_idx).1.min(location.col_idx),
+            ..location
+        })
+    }
+
+    /// Like [`Source::get_byte_line`], but with a column that counts a byte order mark.
+    pub(crate) fn locate_byte(&self, byte_offset: usize) -> Option<Location> {
+        self.locate_text_byte(self.decoded_byte(byte_offset)?)
+    }
+
+    /// Get the byte offset into [`Source::text`] of a byte offset into the bytes that the source was decoded from.
+    fn decoded_byte(&self, byte_offset: usize) -> Option<usize> {
+        match &self.byte_map {
+            Some(byte_map) => byte_map.decoded_offset(byte_offset),
+            None => Some(byte_offset),
         }
     }
 
+    /// Like [`Source::locate_byte`], but with a byte offset into [`Source::text`].
+    fn locate_text_byte(&self, byte_offset: usize) -> Option<Location> {
+        self.lines.with(
+            |end| self.text_to(end),
+            |lines| {
+                lines
+                    .last()
+                    .is_some_and(|line| line.byte_span().end > byte_offset)
+            },
+            |lines| {
+                let idx = lines
+                    .binary_search_by_key(&byte_offset, |line| line.byte_offset)
+                    .unwrap_or_else(|idx| idx.saturating_sub(1));
+                let line = *lines.get(idx)?;
+                // Only the last line can end before the offset, if the offset is past the end of the text.
+                if byte_offset > line.byte_span().end {
+                    return None;
+                }
+                assert!(
+                    byte_offset >= line.byte_offset,
+                    "byte_offset = {}, line.byte_offset = {}",
+                    byte_offset,
+                    line.byte_offset
+                );
+                Some(Location {
+                    line,
+                    line_idx: idx,
+                    col_idx: byte_offset - line.byte_offset,
+                })
+            },
+        )
+    }
+
     /// Get the range of lines that this span runs across.
     ///
     /// The resulting range is guaranteed to contain valid line indices (i.e: those that can be used for
@@ -269,20 +630,270 @@ impl<I: AsRef<str>> Source<I> {
             .map_or(0, |location| location.line_idx);
         let end = self
             .get_offset_line(span.end().saturating_sub(1).max(span.start()))
-            .map_or(self.lines.len(), |location| location.line_idx + 1);
+            .map_or_else(|| self.all_lines().len(), |location| location.line_idx + 1);
         start..end
     }
 
+    /// Get the line ending that terminates a line, if it has one. The last line of a source usually does not.
+    ///
+    /// The line ending is made of the last [`LineEnding::char_len`] characters of the line.
+    pub fn line_ending(&self, line: Line) -> Option<LineEnding> {
+        LineEnding::of(self.get_line_text(line)?)
+    }
+
+    /// Get the style of line endings used throughout the source.
+    pub fn line_endings(&self) -> LineEndings {
+        self.all_lines()
+            .iter()
+            .filter_map(|&line| self.line_ending(line))
+            .fold(LineEndings::None, |endings, ending| match endings {
+                LineEndings::None => LineEndings::Uniform(ending),
+                LineEndings::Uniform(uniform) if uniform == ending => endings,
+                _ => LineEndings::Mixed,
+            })
+    }
+
+    /// Returns `true` if the source starts with a byte order mark (`U+FEFF`).
+    ///
+    /// The byte order mark is still the first character of the source, but it is not counted in columns, either those
+    /// that reports display or those of [`Location`]s and [`Source::line_col_to_offset`].
+    pub fn has_bom(&self) -> bool {
+        self.text_to('\u{FEFF}'.len_utf8())
+            .0
+            .starts_with('\u{FEFF}')
+    }
+
+    /// Get the zero-indexed (character) column of a location that counts a byte order mark, as found by
+    /// [`Source::locate`], without it.
+    pub(crate) fn display_col(&self, location: &Location) -> usize {
+        location
+            .col_idx
+            .saturating_sub(self.bom_len(location.line_idx).0)
+    }
+
+    /// The length in characters and bytes of the byte order mark at the start of a line, if it has one.
+    fn bom_len(&self, line_idx: usize) -> (usize, usize) {
+        if line_idx == 0 && self.has_bom() {
+            (1, '\u{FEFF}'.len_utf8())
+        } else {
+            (0, 0)
+        }
+    }
+
     /// Get the source text for a line, includes trailing whitespace and the newline
     pub fn get_line_text(&self, line: Line) -> Option<&'_ str> {
-        self.text.as_ref().get(line.byte_span())
+        self.text_to(line.byte_span().end).0.get(line.byte_span())
+    }
+
+    /// Get the source text covered by a span of character offsets.
+    pub fn get_span_text<S: Span>(&self, span: &S) -> Option<&'_ str> {
+        let start = self.offset_to_byte(span.start())?;
+        let end = self.offset_to_byte(span.end())?;
+        self.text_to(end).0.get(start..end)
+    }
+
+    /// Get the character offset of a zero-indexed line and (character) column.
+    ///
+    /// The column may be the length of the line, which is the offset of the start of the next line.
+    pub fn line_col_to_offset(&self, line_idx: usize, col_idx: usize) -> Option<usize> {
+        let line = self.line(line_idx)?;
+        let col_idx = col_idx + self.bom_len(line_idx).0;
+        (col_idx <= line.len()).then(|| line.offset + col_idx)
+    }
+
+    /// Get the byte offset, into [`Source::text`], of a zero-indexed line and (character) column.
+    ///
+    /// The column may be the length of the line, which is the offset of the start of the next line.
+    pub fn line_col_to_byte(&self, line_idx: usize, col_idx: usize) -> Option<usize> {
+        let line = self.line(line_idx)?;
+        let col_idx = col_idx + self.bom_len(line_idx).0;
+        Some(line.byte_offset + self.byte_col(line, col_idx)?)
+    }
+
+    /// Get the byte offset, into [`Source::text`], of a character offset.
+    pub fn offset_to_byte(&self, offset: usize) -> Option<usize> {
+        let location = self.locate(offset)?;
+        Some(location.line.byte_offset + self.byte_col(location.line, location.col_idx)?)
+    }
+
+    /// Get the character offset of a byte offset into [`Source::text`], or `None` if it is not at the start of a
+    /// character.
+    ///
+    /// This is the inverse of [`Source::offset_to_byte`]. For sources made with [`Source::decode`], use
+    /// [`Source::original_byte_to_offset`] for byte offsets into the original bytes.
+    pub fn byte_to_offset(&self, byte_offset: usize) -> Option<usize> {
+        let location = self.locate_text_byte(byte_offset)?;
+        let line_text = self.get_line_text(location.line)?;
+        line_text
+            .is_char_boundary(location.col_idx)
+            .then(|| location.line.offset + self.char_col(location.line, location.col_idx))
+    }
+
+    /// Get the character offset of a byte offset into the bytes that the source was decoded from, as used by
+    /// [`IndexType::Byte`](crate::IndexType::Byte) spans.
+    ///
+    /// For sources made with [`Source::decode`], a byte offset within an encoded character gives the offset of that
+    /// character. For other sources, this is the same as [`Source::byte_to_offset`].
+    pub fn original_byte_to_offset(&self, byte_offset: usize) -> Option<usize> {
+        self.byte_to_offset(self.decoded_byte(byte_offset)?)
+    }
+
+    /// Find the byte column of a character column within a line, which may be the length of the line.
+    pub(crate) fn byte_col(&self, line: Line, col_idx: usize) -> Option<usize> {
+        if col_idx > line.char_len {
+            return None;
+        } else if line.char_len == line.byte_len {
+            return Some(col_idx);
+        }
+        let (start_col, start_byte) = self
+            .char_index
+            .as_ref()
+            .map_or((0, 0), |index| index.before_char(line, col_idx));
+        let line_text = self.get_line_text(line)?;
+        line_text[start_byte..]
+            .char_indices()
+            .map(|(idx, _)| start_byte + idx)
+            .chain([line_text.len()])
+            .nth(col_idx - start_col)
+    }
+
+    /// Count the characters of a line that start before the given byte column.
+    pub(crate) fn char_col(&self, line: Line, byte_col: usize) -> usize {
+        let byte_col = byte_col.min(line.byte_len);
+        if line.char_len == line.byte_len {
+            return byte_col;
+        }
+        let (start_col, start_byte) = self
+            .char_index
+            .as_ref()
+            .map_or((0, 0), |index| index.before_byte(line, byte_col));
+        let line_text = self.get_line_text(line).unwrap_or_default();
+        start_col
+            + line_text[start_byte..]
+                .char_indices()
+                .take_while(|(idx, _)| start_byte + idx < byte_col)
+                .count()
     }
 }
 
+impl Source<String> {
+    /// Decode a [`Source`] from bytes in the given encoding.
+    ///
+    /// The source remembers where each character was in `bytes`, so [`IndexType::Byte`](crate::IndexType::Byte) spans
+    /// still refer to offsets in the original bytes, even when they differ from those of the decoded text. Only
+    /// [`Encoding::Utf8`] can fail, when `bytes` is not valid UTF-8.
+    pub fn decode(bytes: Vec<u8>, encoding: Encoding) -> Result<Self, FromUtf8Error> {
+        let (text, byte_map) = decode::decode(bytes, encoding)?;
+        Ok(Self {
+            byte_map,
+            ..Self::from(text)
+        })
+    }
+
+    /// Replace the characters in the given span with `replacement`.
+    ///
+    /// Only the lines touched by the edit are scanned again; the lines after it are kept and moved along. This makes
+    /// it suitable for applying the incremental changes sent by an editor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span is backwards or ends after the end of the source.
+    ///
+    /// Byte offsets of a source made with [`Source::decode`] refer to its text once it has been edited.
+    pub fn edit(&mut self, span: Range<usize>, replacement: &str) {
+        assert!(span.start <= span.end, "Edit start is after its end");
+        let (Some(start), Some(end)) = (
+            self.offset_to_byte(span.start),
+            self.offset_to_byte(span.end),
+        ) else {
+            panic!("Edit span {:?} is out of bounds", span);
+        };
+        // A CR at the end of the previous line may combine with an LF at the start of the edit.
+        let first = self
+            .get_offset_line(span.start)
+            .map_or(0, |location| location.line_idx.saturating_sub(1));
+        self.text.replace_range(start..end, replacement);
+        // The original bytes no longer describe the text.
+        self.byte_map = None;
+
+        let lines = match &mut self.lines {
+            LineIndex::Eager(lines) => lines,
+            LineIndex::Lazy { scanned, complete } => match complete.take() {
+                Some(lines) => {
+                    self.lines = LineIndex::Eager(lines);
+                    let LineIndex::Eager(lines) = &mut self.lines else {
+                        unreachable!()
+                    };
+                    lines
+                }
+                // The lines after the edit have not all been found yet, so leave them to be scanned on demand.
+                None => {
+                    scanned
+                        .get_mut()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .truncate(first);
+                    return;
+                }
+            },
+        };
+
+        let old = lines.split_off(first);
+        let (old_chars, new_chars) = (span.end - span.start, replacement.chars().count());
+        let (old_end, new_end) = (end, start + replacement.len());
+        let (mut char_offset, mut byte_offset) = (old[0].offset, old[0].byte_offset);
+        while byte_offset < self.text.len() {
+            // Past the edit, a line that starts where one used to start is unchanged, as are all of those after it.
+            if byte_offset >= new_end {
+                let old_offset = byte_offset - new_end + old_end;
+                if let Ok(idx) = old.binary_search_by_key(&old_offset, |line| line.byte_offset) {
+                    if let Some(index) = &mut self.char_index {
+                        index.splice(
+                            &self.text,
+                            old[0].offset..old[idx].offset,
+                            &lines[first..],
+                            (old_chars, new_chars),
+                            (old_end, new_end),
+                        );
+                    }
+                    lines.extend(old[idx..].iter().map(|line| Line {
+                        offset: line.offset + new_chars - old_chars,
+                        byte_offset: line.byte_offset + new_end - old_end,
+                        ..*line
+                    }));
+                    return;
+                }
+            }
+            let line = scan_line(&self.text, char_offset, byte_offset);
+            char_offset += line.char_len;
+            byte_offset += line.byte_len;
+            lines.push(line);
+        }
+        // Nothing is left to scan, but a source that is now empty still needs its single empty line.
+        scan_lines(&self.text, lines, |_| true);
+        if let Some(index) = &mut self.char_index {
+            // None of the old lines were kept, so nothing needs to be moved.
+            index.splice(
+                &self.text,
+                old[0].offset..usize::MAX,
+                &lines[first..],
+                (0, 0),
+                (0, 0),
+            );
+        }
+    }
+}
+
+/// The position of an offset within a [`Source`], as found by [`Source::get_offset_line`] or
+/// [`Source::get_byte_line`].
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Location {
+    /// The line that the offset appears on.
     pub line: Line,
+    /// The zero-indexed number of the line.
     pub line_idx: usize,
+    /// The zero-indexed column of the offset within the line, counted in characters or bytes depending on how the
+    /// location was found. A byte order mark at the start of the source is not counted, as in the columns that reports
+    /// display.
     pub col_idx: usize,
 }
 
@@ -341,7 +952,81 @@ impl<I: AsRef<str>, Id: fmt::Display + Eq> Cache<Id> for (Id, &'_ Source<I>) {
 /// A [`Cache`] that fetches [`Source`]s from the filesystem.
 #[derive(Default, Debug, Clone)]
 pub struct FileCache {
-    files: HashMap<PathBuf, Source>,
+    files: HashMap<PathBuf, CachedFile>,
+    reload_on_change: bool,
+    paths: PathPolicy,
+    encoding: Encoding,
+}
+
+#[derive(Debug, Clone)]
+struct CachedFile {
+    source: Source,
+    stamp: Option<FileStamp>,
+}
+
+/// The modification time and size of a file, used to notice when it has changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Result<Self, Error> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+impl FileCache {
+    /// Read relative paths from the given directory, rather than the current directory.
+    ///
+    /// The root is also used by [`PathDisplay::RelativeToRoot`].
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.paths.root = Some(root.into());
+        self
+    }
+
+    /// Specify how the paths of files are displayed in reports.
+    ///
+    /// If unspecified, this is [`PathDisplay::Verbatim`].
+    pub fn with_path_display(mut self, display: PathDisplay) -> Self {
+        self.paths.display = display;
+        self
+    }
+
+    /// Specify the encoding that files are decoded from.
+    ///
+    /// If unspecified, files must be valid UTF-8. See [`Source::decode`].
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Check the modification time and size of cached files each time they are fetched, and reload them if either has
+    /// changed.
+    ///
+    /// This is useful for long-running programs, such as language servers, that keep a cache around while files are
+    /// edited. It is disabled by default, as it costs a filesystem lookup per fetch.
+    pub fn with_reload_on_change(mut self, reload_on_change: bool) -> Self {
+        self.reload_on_change = reload_on_change;
+        self
+    }
+
+    /// Remove the given file from the cache, so that it is read again the next time it is fetched.
+    ///
+    /// Returns `true` if the file was in the cache.
+    pub fn invalidate<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        self.files.remove(path.as_ref()).is_some()
+    }
+
+    /// Remove every file from the cache.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
 }
 
 // this is here for backwards compatibility
@@ -349,14 +1034,31 @@ impl Cache<Path> for FileCache {
     type Storage = String;
 
     fn fetch(&mut self, path: &Path) -> Result<&Source, impl fmt::Debug> {
+        let file_path = self.paths.resolve(path);
+        // The stamp is taken before reading, so that changes made while reading are picked up by the next fetch.
+        let stamp = if self.reload_on_change {
+            Some(FileStamp::of(&file_path)?)
+        } else {
+            None
+        };
         Ok::<_, Error>(match self.files.entry(path.to_path_buf()) {
             // TODO: Don't allocate here
-            Entry::Occupied(entry) => entry.into_mut(),
-            Entry::Vacant(entry) => entry.insert(Source::from(fs::read_to_string(path)?)),
+            Entry::Occupied(entry) if entry.get().stamp == stamp => &entry.into_mut().source,
+            entry => {
+                let source = Source::decode(fs::read(&file_path)?, self.encoding)
+                    .map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+                &entry
+                    .insert_entry(CachedFile { source, stamp })
+                    .into_mut()
+                    .source
+            }
         })
     }
     fn display<'a>(&self, path: &'a Path) -> Option<impl fmt::Display + 'a> {
-        Some(Box::new(path.display()))
+        Some(self.paths.display(path))
+    }
+    fn absolute_path(&self, path: &Path) -> Option<PathBuf> {
+        std::path::absolute(self.paths.resolve(path)).ok()
     }
 }
 
@@ -369,6 +1071,9 @@ impl Cache<&Path> for FileCache {
     fn display<'a>(&self, path: &'a &Path) -> Option<impl fmt::Display + 'a> {
         Cache::<Path>::display(self, *path)
     }
+    fn absolute_path(&self, path: &&Path) -> Option<PathBuf> {
+        Cache::<Path>::absolute_path(self, *path)
+    }
 }
 
 /// A [`Cache`] that fetches [`Source`]s using the provided function.
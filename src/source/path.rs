@@ -0,0 +1,109 @@
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    path::{Component, Path, PathBuf, MAIN_SEPARATOR},
+};
+
+/// How a file cache displays the paths of its sources.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PathDisplay {
+    /// Paths are displayed exactly as they were given.
+    #[default]
+    Verbatim,
+    /// Paths are displayed relative to the cache's root directory, or the current directory if it has none.
+    RelativeToRoot,
+    /// Paths are displayed relative to the current directory.
+    RelativeToCwd,
+    /// Paths are displayed as absolute paths.
+    Absolute,
+    /// Only the file name of each path is displayed.
+    FileName,
+}
+
+/// The root directory and display policy shared by the filesystem caches.
+#[derive(Clone, Debug, Default)]
+pub(super) struct PathPolicy {
+    pub(super) root: Option<PathBuf>,
+    pub(super) display: PathDisplay,
+}
+
+impl PathPolicy {
+    /// The path that the given ID refers to, which is relative to the root directory if it is not absolute.
+    pub(super) fn resolve<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        match &self.root {
+            Some(root) => Cow::Owned(root.join(path)),
+            None => Cow::Borrowed(path),
+        }
+    }
+
+    /// Display the given ID according to the display policy. Every policy apart from [`PathDisplay::Verbatim`] uses
+    /// `/` as the separator, so that output is the same on every platform.
+    pub(super) fn display(&self, path: &Path) -> String {
+        let displayed = match self.display {
+            PathDisplay::Verbatim => return path.display().to_string(),
+            PathDisplay::RelativeToRoot => match &self.root {
+                Some(root) => relative_to(&self.resolve(path), root),
+                None => std::env::current_dir()
+                    .ok()
+                    .and_then(|cwd| relative_to(path, &cwd)),
+            },
+            PathDisplay::RelativeToCwd => std::env::current_dir()
+                .ok()
+                .and_then(|cwd| relative_to(&self.resolve(path), &cwd)),
+            PathDisplay::Absolute => std::path::absolute(self.resolve(path)).ok(),
+            PathDisplay::FileName => path.file_name().map(PathBuf::from),
+        };
+        normalise_separators(displayed.as_deref().unwrap_or(path))
+    }
+}
+
+/// Find the path that leads from `base` to `path`, by comparing their components.
+///
+/// `.` and `..` components are resolved lexically, without following symbolic links. Returns `None` if the paths have no
+/// common root, such as paths on different drives.
+pub(super) fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let components = |path: &Path| {
+        std::path::absolute(path).map(|path| {
+            let mut components = Vec::new();
+            for component in path.components() {
+                match component {
+                    Component::CurDir => {}
+                    // `..` never leaves the root, or the drive on Windows.
+                    Component::ParentDir => {
+                        if let Some(Component::Normal(_)) = components.last() {
+                            components.pop();
+                        }
+                    }
+                    component => components.push(component),
+                }
+            }
+            components
+                .into_iter()
+                .map(|component| component.as_os_str().to_owned())
+                .collect::<Vec<_>>()
+        })
+    };
+    let (path, base) = (components(path).ok()?, components(base).ok()?);
+    if path.first() != base.first() {
+        return None;
+    }
+
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let relative: PathBuf = std::iter::repeat_n(OsStr::new(".."), base.len() - common)
+        .chain(path[common..].iter().map(|c| c.as_os_str()))
+        .collect();
+    Some(if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    })
+}
+
+fn normalise_separators(path: &Path) -> String {
+    let path = path.display().to_string();
+    if MAIN_SEPARATOR == '/' {
+        path
+    } else {
+        path.replace(MAIN_SEPARATOR, "/")
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+
+use std::{
+    borrow::Borrow,
+    sync::{Arc, MutexGuard, TryLockError},
+};
+
+use combinators::Message;
+
+/// A [`Cache`] that can be shared between threads, which fetches [`Source`]s from another cache.
+///
+/// Cloning a [`SharedCache`] gives a new handle to the same underlying cache, which may be sent to another thread. Every
+/// fetch goes through the underlying cache, so settings such as the root, encoding and reloading of a [`FileCache`]
+/// apply to every handle. Handles take turns to use the underlying cache, but only while fetching: the sources that
+/// they lend out are copies, which are shared by every handle. A handle that already has a source doesn't wait for
+/// another to finish with the underlying cache, but lends out the copy that it has.
+///
+/// A source is copied once, and then again only when the underlying cache gives a different source for its ID, such as
+/// after a file has been reloaded. Use a cheaply cloned [`Cache::Storage`], such as `Arc<str>`, to share the text of
+/// each source with the underlying cache rather than copy it.
+///
+/// ```
+/// # use ariadne::{Cache, FnCache, SharedCache};
+/// let cache = SharedCache::new(FnCache::new(|id: &String| Ok::<_, ()>(format!("// the source of {id}"))));
+/// std::thread::scope(|scope| {
+///     for i in 0..4 {
+///         let mut cache = cache.clone();
+///         scope.spawn(move || {
+///             let src = cache.fetch(&format!("module_{i}.rs")).unwrap();
+///             assert_eq!(src.text(), format!("// the source of module_{i}.rs"));
+///         });
+///     }
+/// });
+/// ```
+pub struct SharedCache<C, K, I = String>
+where
+    I: AsRef<str>,
+{
+    shared: Arc<Mutex<Shared<C, K, I>>>,
+    /// The sources that this handle has fetched, which it keeps so that it can lend them out.
+    fetched: HashMap<K, Arc<Source<I>>>,
+}
+
+#[derive(Debug)]
+struct Shared<C, K, I>
+where
+    I: AsRef<str>,
+{
+    cache: C,
+    /// The latest copy of each source fetched from `cache`.
+    sources: HashMap<K, Copied<I>>,
+}
+
+#[derive(Debug)]
+struct Copied<I: AsRef<str>> {
+    copy: Arc<Source<I>>,
+    /// The address and length of the text of the source in `cache` that this is a copy of.
+    ///
+    /// Comparing these is much cheaper than comparing the text. Outside of [`SharedCache::with_inner`], which throws
+    /// every copy away, a cache can only change a source while fetching it, by replacing it. Caches such as
+    /// [`FileCache`] read the new text before dropping the old, so it is at a different address.
+    origin: (usize, usize),
+}
+
+impl<C, K, I> SharedCache<C, K, I>
+where
+    I: AsRef<str>,
+{
+    /// Create a new [`SharedCache`] that fetches sources from the given cache.
+    pub fn new(cache: C) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                cache,
+                sources: HashMap::default(),
+            })),
+            fetched: HashMap::default(),
+        }
+    }
+
+    /// Call a function with the underlying cache, such as to invalidate a [`FileCache`] entry.
+    ///
+    /// Every handle is blocked from fetching sources until the function returns. Since the function may change any
+    /// source, they are all copied again when they are next fetched.
+    pub fn with_inner<R>(&self, f: impl FnOnce(&mut C) -> R) -> R {
+        let mut shared = self.lock();
+        shared.sources.clear();
+        f(&mut shared.cache)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared<C, K, I>> {
+        self.shared.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C, K: Clone, I: AsRef<str>> Clone for SharedCache<C, K, I> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            fetched: self.fetched.clone(),
+        }
+    }
+}
+
+impl<C: fmt::Debug, K: fmt::Debug, I: AsRef<str> + fmt::Debug> fmt::Debug for SharedCache<C, K, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedCache")
+            .field("shared", &self.shared)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Id, C, K, I> Cache<Id> for SharedCache<C, K, I>
+where
+    Id: Eq + Hash + ToOwned<Owned = K> + ?Sized,
+    K: Eq + Hash + Borrow<Id>,
+    I: AsRef<str> + Clone,
+    C: Cache<Id, Storage = I>,
+{
+    type Storage = I;
+
+    fn fetch(&mut self, id: &Id) -> Result<&Source<I>, impl fmt::Debug> {
+        let src = {
+            let mut shared = match self.shared.try_lock() {
+                Ok(shared) => shared,
+                Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                // Rather than wait for another handle to finish with the underlying cache, lend out the copy that this
+                // handle already has. A change to the source is seen by the next fetch that gets the lock.
+                Err(TryLockError::WouldBlock) if self.fetched.contains_key(id) => {
+                    return Ok(&*self.fetched[id]);
+                }
+                Err(TryLockError::WouldBlock) => self.lock(),
+            };
+            let Shared { cache, sources } = &mut *shared;
+            // The error may borrow the underlying cache, so it can't outlive the lock.
+            let src = cache.fetch(id).map_err(|e| Message(format!("{e:?}")))?;
+            let origin = (src.text().as_ptr() as usize, src.text().len());
+            let copied = || Copied {
+                copy: Arc::new(src.clone()),
+                origin,
+            };
+            match sources.get_mut(id) {
+                Some(copied) if copied.origin == origin => copied.copy.clone(),
+                Some(old) => {
+                    *old = copied();
+                    old.copy.clone()
+                }
+                None => sources
+                    .entry(id.to_owned())
+                    .or_insert_with(copied)
+                    .copy
+                    .clone(),
+            }
+        };
+        match self.fetched.get_mut(id) {
+            Some(fetched) if Arc::ptr_eq(fetched, &src) => {}
+            Some(fetched) => *fetched = src,
+            None => {
+                self.fetched.insert(id.to_owned(), src);
+            }
+        }
+        Ok::<_, Message>(&self.fetched[id])
+    }
+    fn display<'a>(&self, id: &'a Id) -> Option<impl fmt::Display + 'a> {
+        let display = self
+            .lock()
+            .cache
+            .display(id)
+            .map(|display| display.to_string());
+        display
+    }
+    fn absolute_path(&self, id: &Id) -> Option<PathBuf> {
+        self.lock().cache.absolute_path(id)
+    }
+}
@@ -1,23 +1,24 @@
 use std::iter::zip;
+use std::ops::Range;
 use std::sync::Arc;
 
-use super::Source;
+use super::{LineIndex, Source};
 
 fn test_with_lines(lines: Vec<&str>) {
     let source: String = lines.iter().copied().collect();
     let source = Source::from(source);
 
-    assert_eq!(source.lines.len(), lines.len());
+    assert_eq!(source.lines().len(), lines.len());
 
     let mut offset = 0;
-    for (source_line, raw_line) in zip(source.lines.iter().copied(), lines.into_iter()) {
+    for (source_line, raw_line) in zip(source.lines(), lines) {
         assert_eq!(source_line.offset, offset);
         assert_eq!(source_line.char_len, raw_line.chars().count());
         assert_eq!(source.get_line_text(source_line).unwrap(), raw_line);
         offset += source_line.char_len;
     }
 
-    assert_eq!(source.len, offset);
+    assert_eq!(source.len(), offset);
 }
 
 #[test]
@@ -66,17 +67,17 @@ fn source_from_other_string_types() {
     let arc = Arc::from(raw);
     let source = Source::from(arc);
 
-    assert_eq!(source.lines.len(), 4);
+    assert_eq!(source.lines().len(), 4);
 
     let mut offset = 0;
-    for (source_line, raw_line) in zip(source.lines.iter().copied(), raw.split_inclusive('\n')) {
+    for (source_line, raw_line) in zip(source.lines(), raw.split_inclusive('\n')) {
         assert_eq!(source_line.offset, offset);
         assert_eq!(source_line.char_len, raw_line.chars().count());
         assert_eq!(source.get_line_text(source_line).unwrap(), raw_line);
         offset += source_line.char_len;
     }
 
-    assert_eq!(source.len, offset);
+    assert_eq!(source.len(), offset);
 }
 
 #[test]
@@ -90,5 +91,606 @@ fn source_from_reference() {
     }
 
     let source = non_owning_source(raw);
-    assert_eq!(source.lines.len(), 3);
+    assert_eq!(source.lines().len(), 3);
+}
+
+#[test]
+fn lazy_source_matches_eager() {
+    let raw = "fn main() {\r\n    let x = \"héllo\";\n\n\u{2028}    x\r}";
+    let eager = Source::from(raw);
+
+    for offset in 0..=raw.chars().count() + 1 {
+        assert_eq!(
+            Source::lazy(raw).get_offset_line(offset),
+            eager.get_offset_line(offset)
+        );
+    }
+    for byte_offset in 0..=raw.len() + 1 {
+        assert_eq!(
+            Source::lazy(raw).get_byte_line(byte_offset),
+            eager.get_byte_line(byte_offset)
+        );
+    }
+    for idx in 0..=eager.lines().len() {
+        assert_eq!(Source::lazy(raw).line(idx), eager.line(idx));
+    }
+
+    let lazy = Source::lazy(raw);
+    assert_eq!(lazy.len(), eager.len());
+    assert!(lazy.lines().eq(eager.lines()));
+    assert_eq!(
+        lazy.get_line_range(&(3..40)),
+        eager.get_line_range(&(3..40))
+    );
+    assert!(Source::lazy("").lines().eq(Source::from("").lines()));
+}
+
+#[test]
+fn lazy_source_scans_on_demand() {
+    let raw = "a\nb\nc\nd\ne\n";
+    let source = Source::lazy(raw);
+    let scanned = |source: &Source<&str>| match &source.lines {
+        LineIndex::Lazy { scanned, .. } => scanned.lock().unwrap().len(),
+        LineIndex::Eager(_) => unreachable!(),
+    };
+
+    assert_eq!(source.get_offset_line(2).unwrap().line_idx, 1);
+    assert_eq!(scanned(&source), 2);
+    assert_eq!(source.line(2).unwrap().offset(), 4);
+    assert_eq!(scanned(&source), 3);
+    assert_eq!(source.get_offset_line(1).unwrap().line_idx, 0);
+    assert_eq!(scanned(&source), 3);
+
+    assert_eq!(source.lines().len(), 5);
+    assert_eq!(source.clone().line(4), source.line(4));
+}
+
+#[test]
+fn lazy_source_reads_in_chunks() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FURTHEST: AtomicUsize = AtomicUsize::new(0);
+    // Like `MappedText::read_to`, only return whole characters, and record how far the text has been read.
+    fn read_to<'a>(text: &'a &str, end: usize) -> (&'a str, bool) {
+        let end = (0..=end.min(text.len()))
+            .rev()
+            .find(|&end| text.is_char_boundary(end))
+            .unwrap();
+        FURTHEST.fetch_max(end, Ordering::Relaxed);
+        (&text[..end], end == text.len())
+    }
+
+    // Multi-byte characters and CRLFs that straddle the ends of chunks, and a line longer than two chunks.
+    let raw: String = (0..2000)
+        .map(|i| match i {
+            1000 => format!("{}\n", "y".repeat(150_000)),
+            _ => format!("{}é\r\n{}\n", "x".repeat(i % 97), "ü".repeat(i % 13)),
+        })
+        .collect();
+    let eager = Source::from(raw.as_str());
+    let chunked = || Source {
+        read_to: Some(read_to),
+        ..Source::lazy(raw.as_str())
+    };
+
+    let source = chunked();
+    assert_eq!(source.line(1), eager.line(1));
+    assert!(FURTHEST.load(Ordering::Relaxed) < raw.len() / 2);
+    // Each pass looks further into the same source, so that its lines are indexed a few at a time.
+    let source = chunked();
+    for idx in (0..eager.lines().len()).step_by(97) {
+        assert_eq!(source.line(idx), eager.line(idx));
+    }
+    let source = chunked();
+    for offset in (0..eager.len()).step_by(eager.len() / 53) {
+        assert_eq!(
+            source.get_offset_line(offset),
+            eager.get_offset_line(offset)
+        );
+    }
+    let source = chunked();
+    for byte_offset in (0..=raw.len() + 1).step_by(raw.len() / 59) {
+        assert_eq!(
+            source.get_byte_line(byte_offset),
+            eager.get_byte_line(byte_offset)
+        );
+    }
+    assert_eq!(chunked().get_byte_line(raw.len() + 1), None);
+    assert!(chunked().lines().eq(eager.lines()));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mmap_file_cache() {
+    use super::{Cache, MmapFileCache};
+
+    let path = std::env::temp_dir().join(format!("ariadne-mmap-{}.txt", std::process::id()));
+    std::fs::write(&path, "first\nsecond\r\nthird").unwrap();
+
+    // SAFETY: nothing else writes to the file while it is cached.
+    let mut cache = unsafe { MmapFileCache::new() };
+    let source = cache.fetch(path.as_path()).unwrap();
+    let location = source.get_offset_line(8).unwrap();
+    assert_eq!(location.line_idx, 1);
+    assert_eq!(source.get_line_text(location.line), Some("second\r\n"));
+    assert_eq!(source.len(), 19);
+    cache.clear();
+
+    // Invalid UTF-8 far into a file is not read until it is needed, and then ends the text.
+    let mut late = "line\n".repeat(100_000).into_bytes();
+    late.extend([0xff, b'\n']);
+    std::fs::write(&path, &late).unwrap();
+    let source = cache.fetch(path.as_path()).unwrap();
+    assert_eq!(source.line(3).map(|line| line.offset()), Some(15));
+    assert!(
+        source
+            .text
+            .checked
+            .load(std::sync::atomic::Ordering::Relaxed)
+            < late.len() / 2
+    );
+    assert_eq!(source.line(100_000), None);
+    assert_eq!(source.text().len(), 500_000);
+    cache.clear();
+
+    // The same goes for invalid UTF-8 at the start of a file.
+    let early = [b"ok\n".as_slice(), &[0xff, 0xfe], b"\nmore"].concat();
+    std::fs::write(&path, &early).unwrap();
+    let source = cache.fetch(path.as_path()).unwrap();
+    assert_eq!(source.text(), "ok\n");
+    assert_eq!(source.line(1), None);
+    cache.clear();
+
+    // Strict caches fail to fetch files with invalid UTF-8 anywhere in them.
+    // SAFETY: as above.
+    let mut strict = unsafe { MmapFileCache::new() }.with_strict_utf8(true);
+    for bytes in [&late, &early] {
+        std::fs::write(&path, bytes).unwrap();
+        assert!(strict.fetch(path.as_path()).is_err());
+    }
+    std::fs::write(&path, "valid").unwrap();
+    assert_eq!(strict.fetch(path.as_path()).unwrap().text(), "valid");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn shared_file_cache() {
+    use super::{Cache, FileCache, PathDisplay, SharedCache};
+    use std::path::Path;
+
+    let root = std::env::temp_dir().join(format!("ariadne-shared-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("a.txt"), "old").unwrap();
+
+    // The root, path display and reloading of the file cache apply to every handle.
+    let cache = SharedCache::new(
+        FileCache::default()
+            .with_root(&root)
+            .with_path_display(PathDisplay::FileName)
+            .with_reload_on_change(true),
+    );
+    let id = Path::new("a.txt");
+    let text = |cache: &mut SharedCache<_, _>| cache.fetch(id).unwrap().text().to_string();
+    let mut handles: Vec<_> = (0..4).map(|_| cache.clone()).collect();
+    std::thread::scope(|scope| {
+        for handle in &mut handles {
+            scope.spawn(move || assert_eq!(text(handle), "old"));
+        }
+    });
+    assert_eq!(cache.display(id).unwrap().to_string(), "a.txt");
+    // Every handle lends out the same copy of the source.
+    let copy = |cache: &mut SharedCache<_, _>| cache.fetch(id).unwrap().text().as_ptr();
+    let old = copy(&mut handles[0]);
+    assert!(handles.iter_mut().all(|handle| copy(handle) == old));
+
+    std::fs::write(root.join("a.txt"), "newer").unwrap();
+    assert_eq!(text(&mut handles[0]), "newer");
+    assert_eq!(text(&mut handles[1]), "newer");
+    assert_eq!(copy(&mut handles[0]), copy(&mut handles[1]));
+
+    // Changes made through the underlying cache are seen by every handle.
+    std::fs::write(root.join("a.txt"), "newest").unwrap();
+    cache.with_inner(|inner| {
+        assert!(inner.invalidate(id));
+        assert_eq!(inner.fetch(id).unwrap().text(), "newest");
+    });
+    assert_eq!(text(&mut handles[2]), "newest");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn file_cache_reload() {
+    use super::{Cache, FileCache};
+
+    let path = std::env::temp_dir().join(format!("ariadne-reload-{}.txt", std::process::id()));
+    let text = |cache: &mut FileCache| cache.fetch(path.as_path()).unwrap().text().to_string();
+    std::fs::write(&path, "old").unwrap();
+
+    let mut cache = FileCache::default();
+    let mut reloading = FileCache::default().with_reload_on_change(true);
+    assert_eq!(text(&mut cache), "old");
+    assert_eq!(text(&mut reloading), "old");
+
+    std::fs::write(&path, "newer").unwrap();
+    assert_eq!(text(&mut cache), "old");
+    assert_eq!(text(&mut reloading), "newer");
+
+    assert!(cache.invalidate(&path));
+    assert!(!cache.invalidate(&path));
+    assert_eq!(text(&mut cache), "newer");
+
+    std::fs::write(&path, "newest").unwrap();
+    cache.clear();
+    assert_eq!(text(&mut cache), "newest");
+    std::fs::remove_file(path).unwrap();
+}
+
+fn test_edit(raw: &str, span: Range<usize>, replacement: &str) {
+    let mut expected: Vec<char> = raw.chars().collect();
+    expected.splice(span.clone(), replacement.chars());
+    let expected = Source::from(expected.into_iter().collect::<String>());
+
+    let mut eager = Source::from(raw.to_string());
+    eager.edit(span.clone(), replacement);
+    assert_eq!(eager.text(), expected.text());
+    assert!(
+        eager.lines().eq(expected.lines()),
+        "editing {:?} at {:?} with {:?}",
+        raw,
+        span,
+        replacement
+    );
+
+    let mut lazy = Source::lazy(raw.to_string());
+    lazy.line(1);
+    lazy.edit(span, replacement);
+    assert!(lazy.lines().eq(expected.lines()));
+}
+
+#[test]
+fn source_edit() {
+    test_edit("", 0..0, "new\ntext");
+    test_edit("one\ntwo\nthree", 5..5, "\n");
+    test_edit("one\ntwo\nthree", 2..9, "");
+    test_edit("one\ntwo\nthree", 0..13, "");
+    test_edit("one\ntwo\n", 8..8, "three");
+    test_edit("a\rb", 2..2, "\n");
+    test_edit("a\r\nb", 2..2, "x");
+    test_edit("a\r\nb", 1..2, "");
+    test_edit("héllo\nwörld\n", 1..8, "ü\u{2028}");
+}
+
+#[test]
+fn source_edit_matches_rebuild() {
+    let raw = "a\r\nbé\n\rc\u{2028}\n";
+    let len = raw.chars().count();
+    for start in 0..=len {
+        for end in start..=len {
+            for replacement in ["", "x", "\n", "\r", "y\r\nz"] {
+                test_edit(raw, start..end, replacement);
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn source_edit_out_of_bounds() {
+    Source::from("abc".to_string()).edit(2..4, "");
+}
+
+#[test]
+fn relative_paths() {
+    use super::path::relative_to;
+    use std::path::Path;
+
+    let relative = |path: &str, base: &str| relative_to(Path::new(path), Path::new(base)).unwrap();
+    assert_eq!(relative("/a/b/c.rs", "/a"), Path::new("b/c.rs"));
+    assert_eq!(relative("/a/b/c.rs", "/a/d/e"), Path::new("../../b/c.rs"));
+    assert_eq!(relative("/a/./b", "/a/b"), Path::new("."));
+    assert_eq!(relative("/a/b/../c.rs", "/a"), Path::new("c.rs"));
+    assert_eq!(relative("/a/c.rs", "/a/b/.."), Path::new("c.rs"));
+    assert_eq!(relative("/../a/c.rs", "/a/b"), Path::new("../c.rs"));
+}
+
+#[test]
+fn file_cache_path_display() {
+    use super::{Cache, FileCache, PathDisplay};
+    use std::path::Path;
+
+    // Tests run in the package directory, so the root is relative to the current directory.
+    let root = Path::new("target").join(format!("ariadne-root-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("src").join("main.rs"), "fn main() {}").unwrap();
+
+    let id = Path::new("src").join("main.rs");
+    let display = |path_display| {
+        let mut cache = FileCache::default()
+            .with_root(root.join("src").join(".."))
+            .with_path_display(path_display);
+        assert_eq!(cache.fetch(id.as_path()).unwrap().text(), "fn main() {}");
+        cache.display(id.as_path()).unwrap().to_string()
+    };
+    assert_eq!(display(PathDisplay::RelativeToRoot), "src/main.rs");
+    assert_eq!(display(PathDisplay::FileName), "main.rs");
+    assert!(display(PathDisplay::Absolute).ends_with("/src/main.rs"));
+    assert_eq!(
+        display(PathDisplay::RelativeToCwd),
+        format!("target/ariadne-root-{}/src/main.rs", std::process::id())
+    );
+    assert_eq!(display(PathDisplay::Verbatim), id.display().to_string());
+
+    let cache = FileCache::default().with_path_display(PathDisplay::RelativeToCwd);
+    let display = cache.display(Path::new("src/lib.rs")).unwrap().to_string();
+    assert_eq!(display, "src/lib.rs");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn source_decode() {
+    use super::Encoding;
+
+    let decode = |bytes: &[u8], encoding| Source::decode(bytes.to_vec(), encoding);
+    // Byte offsets into the original bytes, and the decoded char offsets that they should point at.
+    let offsets = |source: &Source, expected: &[(usize, usize)]| {
+        for &(byte_offset, offset) in expected {
+            let location = source.get_byte_line(byte_offset).unwrap();
+            let line_text = source.get_line_text(location.line).unwrap();
+            assert_eq!(
+                location.line.offset() + line_text[..location.col_idx].chars().count(),
+                offset,
+                "byte offset {}",
+                byte_offset
+            );
+        }
+    };
+
+    assert!(decode(b"caf\xe9", Encoding::Utf8).is_err());
+
+    let lossy = decode(b"a\xe2\x82b\nc", Encoding::Utf8Lossy).unwrap();
+    assert_eq!(lossy.text(), "a\u{FFFD}b\nc");
+    offsets(&lossy, &[(0, 0), (1, 1), (2, 1), (3, 2), (5, 4), (6, 5)]);
+    assert_eq!(lossy.get_byte_line(7), None);
+
+    let latin1 = decode(b"caf\xe9\nna\xefve", Encoding::Latin1).unwrap();
+    assert_eq!(latin1.text(), "café\nnaïve");
+    offsets(&latin1, &[(3, 3), (4, 4), (5, 5), (7, 7), (8, 8), (10, 10)]);
+
+    let utf16 = [
+        0xFF, 0xFE, b'h', 0, 0xE9, 0, b'\n', 0, 0x3D, 0xD8, 0x00, 0xDE, b'!', 0,
+    ];
+    let utf16_le = decode(&utf16, Encoding::Utf16).unwrap();
+    assert_eq!(utf16_le.text(), "hé\n😀!");
+    offsets(
+        &utf16_le,
+        &[
+            (0, 0),
+            (2, 0),
+            (4, 1),
+            (6, 2),
+            (8, 3),
+            (10, 3),
+            (12, 4),
+            (14, 5),
+        ],
+    );
+
+    let utf16_be = decode(&[0xFE, 0xFF, 0, b'o', 0, b'k', 0xD8], Encoding::Utf16).unwrap();
+    assert_eq!(utf16_be.text(), "ok\u{FFFD}");
+    offsets(&utf16_be, &[(4, 1), (6, 2), (7, 3)]);
+
+    let no_bom = decode(&[b'h', 0, b'i', 0], Encoding::Utf16).unwrap();
+    assert_eq!(no_bom.text(), "hi");
+}
+
+#[test]
+fn decoded_offset_conversions() {
+    use super::Encoding;
+
+    // Each accented character is one byte in Latin-1 but two in the decoded text.
+    let source = Source::decode(b"caf\xe9\nna\xefve".to_vec(), Encoding::Latin1).unwrap();
+    for offset in 0..=source.len() {
+        let byte_offset = source.offset_to_byte(offset).unwrap();
+        assert!(source.text().is_char_boundary(byte_offset));
+        assert_eq!(source.byte_to_offset(byte_offset), Some(offset));
+    }
+    assert_eq!(source.offset_to_byte(5), Some(6));
+    assert_eq!(source.byte_to_offset(4), None);
+    assert_eq!(source.byte_to_offset(12), Some(10));
+    assert_eq!(source.byte_to_offset(13), None);
+
+    // The original bytes line up with the characters here, and there is one byte fewer than in the text.
+    for offset in 0..=source.len() {
+        assert_eq!(source.original_byte_to_offset(offset), Some(offset));
+    }
+    assert_eq!(source.original_byte_to_offset(11), None);
+
+    let plain = Source::from("naïve");
+    assert_eq!(plain.original_byte_to_offset(3), plain.byte_to_offset(3));
+    assert_eq!(plain.original_byte_to_offset(4), Some(3));
+}
+
+#[test]
+fn file_cache_encoding() {
+    use super::{Cache, Encoding, FileCache};
+
+    let path = std::env::temp_dir().join(format!("ariadne-latin1-{}.txt", std::process::id()));
+    std::fs::write(&path, b"caf\xe9").unwrap();
+    assert!(FileCache::default().fetch(path.as_path()).is_err());
+
+    let mut cache = FileCache::default().with_encoding(Encoding::Latin1);
+    assert_eq!(cache.fetch(path.as_path()).unwrap().text(), "café");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn cache_combinators() {
+    use super::{sources, Cache, FallbackCache, FileCache, MapIdCache, OverlayCache};
+    use std::path::Path;
+
+    let repl = sources([("<repl>", "1 + 2")]);
+    let expansions = sources([("<macro>", "x * 2")]);
+    let mut fallback = FallbackCache::new(repl, expansions);
+    assert_eq!(fallback.fetch(&"<repl>").unwrap().text(), "1 + 2");
+    assert_eq!(fallback.fetch(&"<macro>").unwrap().text(), "x * 2");
+    assert!(fallback.fetch(&"<missing>").is_err());
+    assert_eq!(fallback.display(&"<macro>").unwrap().to_string(), "<macro>");
+
+    let names = ["main.rs", "lib.rs"];
+    let files = sources([("main.rs", "fn main() {}"), ("lib.rs", "pub mod a;")]);
+    let mut mapped = MapIdCache::new(files, |idx: &usize| names[*idx]);
+    assert_eq!(mapped.fetch(&1).unwrap().text(), "pub mod a;");
+    assert_eq!(mapped.display(&0).unwrap().to_string(), "main.rs");
+
+    let path = std::env::temp_dir().join(format!("ariadne-overlay-{}.txt", std::process::id()));
+    std::fs::write(&path, "on disk").unwrap();
+    let mut overlay = OverlayCache::new(FileCache::default());
+    assert_eq!(overlay.fetch(path.as_path()).unwrap().text(), "on disk");
+    assert!(overlay
+        .insert(path.clone(), "unsaved".to_string())
+        .is_none());
+    assert_eq!(overlay.fetch(path.as_path()).unwrap().text(), "unsaved");
+    assert_eq!(
+        overlay.display(path.as_path()).unwrap().to_string(),
+        path.display().to_string()
+    );
+    assert!(overlay.remove(path.as_path()).is_some());
+    assert_eq!(overlay.fetch(Path::new(&path)).unwrap().text(), "on disk");
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn offset_conversions() {
+    let source = Source::from("héllo\r\nwörld\n");
+
+    assert_eq!(source.line_col_to_offset(1, 2), Some(9));
+    assert_eq!(source.line_col_to_offset(0, 7), Some(7));
+    assert_eq!(source.line_col_to_offset(0, 8), None);
+    assert_eq!(source.line_col_to_offset(2, 0), None);
+    assert_eq!(source.line_col_to_byte(1, 2), Some(11));
+    assert_eq!(source.line_col_to_byte(1, 3), Some(12));
+    assert_eq!(source.line_col_to_byte(1, 6), Some(15));
+    assert_eq!(source.line_col_to_byte(1, 7), None);
+
+    for (offset, byte_offset) in [(0, 0), (1, 1), (2, 3), (7, 8), (8, 9), (9, 11), (13, 15)] {
+        assert_eq!(source.offset_to_byte(offset), Some(byte_offset));
+        assert_eq!(source.byte_to_offset(byte_offset), Some(offset));
+    }
+    assert_eq!(source.byte_to_offset(2), None);
+    assert_eq!(source.byte_to_offset(10), None);
+    assert_eq!(source.byte_to_offset(16), None);
+    assert_eq!(source.offset_to_byte(14), None);
+
+    assert_eq!(source.get_span_text(&(1..5)), Some("éllo"));
+    assert_eq!(source.get_span_text(&(7..13)), Some("wörld\n"));
+    assert_eq!(source.get_span_text(&(7..14)), None);
+
+    let location = source.get_offset_line(10).unwrap();
+    assert_eq!((location.line_idx, location.col_idx), (1, 3));
+    assert_eq!(location.line.byte_span(), 8..15);
+}
+
+fn assert_same_conversions(indexed: &Source, plain: &Source) {
+    assert_eq!(indexed.text(), plain.text());
+    for offset in 0..=plain.len() + 1 {
+        assert_eq!(indexed.offset_to_byte(offset), plain.offset_to_byte(offset));
+    }
+    for byte_offset in 0..=plain.text().len() + 1 {
+        assert_eq!(
+            indexed.byte_to_offset(byte_offset),
+            plain.byte_to_offset(byte_offset)
+        );
+        if let Some(location) = plain.get_byte_line(byte_offset) {
+            assert_eq!(
+                indexed.char_col(location.line, location.col_idx),
+                plain.char_col(location.line, location.col_idx)
+            );
+        }
+    }
+}
+
+#[test]
+fn char_index() {
+    let raw = format!(
+        "ascii only\n{}\r\n{}{}\n",
+        "αβγδ ".repeat(40),
+        "x".repeat(100),
+        "😀é".repeat(50)
+    );
+    let indexed = Source::from(raw.clone()).with_char_index(true);
+    assert_same_conversions(&indexed, &Source::from(raw.clone()));
+
+    for (span, replacement) in [
+        (20..30, "ω"),
+        (5..5, "\n"),
+        (100..250, ""),
+        (0..11, "😀😀\n"),
+        (200..260, "a\r\nb"),
+    ] {
+        let mut edited = Source::from(raw.clone()).with_char_index(true);
+        edited.edit(span.clone(), replacement);
+        let mut expected = Source::from(raw.clone());
+        expected.edit(span, replacement);
+        assert_same_conversions(&edited, &expected);
+
+        let rebuilt = Source::from(edited.text().to_string()).with_char_index(true);
+        assert_eq!(edited.char_index, rebuilt.char_index);
+    }
+}
+
+#[test]
+fn line_endings() {
+    use super::{LineEnding, LineEndings};
+
+    let endings = |raw: &str| Source::from(raw).line_endings();
+    assert_eq!(endings("no newline"), LineEndings::None);
+    assert_eq!(endings("a\nb\n"), LineEndings::Uniform(LineEnding::Lf));
+    assert_eq!(
+        endings("a\r\nb\r\nc"),
+        LineEndings::Uniform(LineEnding::CrLf)
+    );
+    assert_eq!(endings("a\r\nb\nc"), LineEndings::Mixed);
+
+    let source = Source::from("a\r\nb\rc\u{2028}d");
+    let line_endings: Vec<_> = source
+        .lines()
+        .map(|line| source.line_ending(line))
+        .collect();
+    assert_eq!(
+        line_endings,
+        [
+            Some(LineEnding::CrLf),
+            Some(LineEnding::Cr),
+            Some(LineEnding::LineSeparator),
+            None
+        ]
+    );
+    let first = source.line(0).unwrap();
+    let ending = LineEnding::CrLf;
+    assert_eq!(
+        source.get_span_text(&(first.span().end - ending.char_len()..first.span().end)),
+        Some(ending.as_str())
+    );
+
+    assert!(Source::from("\u{FEFF}bom").has_bom());
+    assert!(!Source::from("no bom").has_bom());
+}
+
+#[test]
+fn bom_offset_conversions() {
+    let source = Source::from("\u{FEFF}ab\ncd");
+    // Columns of the first line start after the byte order mark, and those of the others are unaffected.
+    for (offset, line_idx, col_idx) in [(1, 0, 0), (2, 0, 1), (3, 0, 2), (4, 1, 0), (6, 1, 2)] {
+        let location = source.get_offset_line(offset).unwrap();
+        assert_eq!((location.line_idx, location.col_idx), (line_idx, col_idx));
+        assert_eq!(source.line_col_to_offset(line_idx, col_idx), Some(offset));
+
+        let byte_offset = source.line_col_to_byte(line_idx, col_idx).unwrap();
+        assert_eq!(source.byte_to_offset(byte_offset), Some(offset));
+        let location = source.get_byte_line(byte_offset).unwrap();
+        assert_eq!((location.line_idx, location.col_idx), (line_idx, col_idx));
+    }
+    assert_eq!(source.get_offset_line(0).unwrap().col_idx, 0);
+    assert_eq!(source.line_col_to_offset(0, 4), None);
+    assert_eq!(source.line_col_to_byte(0, 4), None);
 }